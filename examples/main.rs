@@ -1,21 +1,15 @@
-use std::{fs::File, io::Read, io::Result};
+use std::io::Result;
 
 fn main() -> Result<()> {
-    let bytes = read_as_bytes()?;
-    let encoded = iterm2img::from_bytes(bytes)
+    println!("image:");
+
+    iterm2img::from_file("./examples/image.jpg")?
         .width(5)
         .preserve_aspect_ratio(true)
         .inline(true)
-        .build();
+        .print()?;
 
-    println!("image:\n{}", encoded);
+    println!();
 
     Ok(())
 }
-
-fn read_as_bytes() -> Result<Vec<u8>> {
-    let mut f = File::open("./examples/image.jpg")?;
-    let mut buf = Vec::new();
-    f.read_to_end(&mut buf)?;
-    Ok(buf)
-}
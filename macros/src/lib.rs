@@ -0,0 +1,97 @@
+//! Compile-time image embedding for [iterm2img](https://docs.rs/iterm2img)
+//!
+//! Use the macro through the `macros` feature of `iterm2img`.
+
+use std::path::PathBuf;
+
+use base64::Engine;
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Expr, Ident, LitStr, Token,
+};
+
+struct Input {
+    path: LitStr,
+    options: Punctuated<Setter, Token![,]>,
+}
+
+/// `key = value`, called as the builder method `key(value)`
+struct Setter {
+    key: Ident,
+    value: Expr,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let options = if input.is_empty() {
+            Punctuated::new()
+        } else {
+            input.parse::<Token![,]>()?;
+            Punctuated::parse_terminated(input)?
+        };
+        Ok(Input { path, options })
+    }
+}
+
+impl Parse for Setter {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(Setter { key, value })
+    }
+}
+
+/// embeds the file and returns `iterm2img::Builder` with the payload encoded at compile time
+///
+/// The path is relative to the directory containing the manifest of the calling crate.
+/// `name` is set to the filename, and `key = value` options call the builder method of the same name.
+///
+/// ```ignore
+/// let logo = iterm2img::iterm2img!("logo.png", width = 20, inline = true);
+/// println!("{}", logo);
+/// ```
+#[proc_macro]
+pub fn iterm2img(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Input);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: Input) -> syn::Result<proc_macro2::TokenStream> {
+    let dir = std::env::var_os("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let path = PathBuf::from(dir).join(input.path.value());
+    let bytes = std::fs::read(&path).map_err(|e| {
+        let msg = format!("failed to read {}: {}", path.display(), e);
+        syn::Error::new(input.path.span(), msg)
+    })?;
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+    let len = bytes.len();
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    // `include_bytes!` is only used so that the file is tracked for recompilation
+    let tracked = path
+        .to_str()
+        .ok_or_else(|| syn::Error::new(input.path.span(), "path must be valid UTF-8"))?;
+    let setters = input.options.iter().map(|Setter { key, value }| {
+        quote! { .#key(#value) }
+    });
+
+    Ok(quote! {
+        {
+            const _: &[u8] = ::core::include_bytes!(#tracked);
+            ::iterm2img::from_base64(::core::convert::Into::into(#encoded), #len)
+                .name(#name)
+                #(#setters)*
+        }
+    })
+}
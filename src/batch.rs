@@ -0,0 +1,168 @@
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{from_bytes, Builder};
+
+/// builder for multiple images
+///
+/// # Examples
+///
+/// ```
+/// let result = iterm2img::Batch::new()
+///     .defaults(|b| b.width(10).inline(true))
+///     .push(iterm2img::from_bytes("abc".as_bytes()))
+///     .push(iterm2img::from_bytes("xyz".as_bytes()).width(20))
+///     .build();
+///
+/// let expected = concat!(
+///     "\x1b]1337;File=size=3;width=10;inline=1:YWJj\u{0007}",
+///     "\x1b]1337;File=size=3;width=20;inline=1:eHl6\u{0007}",
+/// );
+/// assert_eq!(result, expected);
+/// ```
+pub struct Batch<'a> {
+    defaults: Builder<'static>,
+    builders: Vec<Builder<'a>>,
+}
+
+impl<'a> Batch<'a> {
+    /// returns empty batch
+    pub fn new() -> Batch<'a> {
+        Batch {
+            defaults: from_bytes(Vec::new()),
+            builders: Vec::new(),
+        }
+    }
+
+    /// set options applied to images that do not set them
+    pub fn defaults(mut self, f: impl FnOnce(Builder<'static>) -> Builder<'static>) -> Batch<'a> {
+        self.defaults = f(self.defaults);
+        self
+    }
+
+    /// add image
+    pub fn push(mut self, builder: Builder<'a>) -> Batch<'a> {
+        self.builders.push(builder);
+        self
+    }
+
+    /// build concatenated string
+    pub fn build(self) -> String {
+        let defaults = self.defaults;
+        self.builders
+            .into_iter()
+            .map(|b| b.with_defaults(&defaults).build())
+            .collect()
+    }
+
+    /// build concatenated string, encoding the images in parallel
+    ///
+    /// The sequences are concatenated in the order the images were added.
+    #[cfg(feature = "rayon")]
+    pub fn build_parallel(self) -> String {
+        use rayon::prelude::*;
+
+        let defaults = self.defaults;
+        let sequences: Vec<String> = self
+            .builders
+            .into_par_iter()
+            .map(|b| b.with_defaults(&defaults).build())
+            .collect();
+        sequences.concat()
+    }
+
+    /// write sequences to writer one by one
+    #[cfg(feature = "std")]
+    pub fn write_to(self, w: &mut impl io::Write) -> io::Result<()> {
+        let defaults = self.defaults;
+        for builder in self.builders {
+            builder.with_defaults(&defaults).write_to(w)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Batch<'_> {
+    fn default() -> Self {
+        Batch::new()
+    }
+}
+
+impl<'a> Extend<Builder<'a>> for Batch<'a> {
+    fn extend<T: IntoIterator<Item = Builder<'a>>>(&mut self, iter: T) {
+        self.builders.extend(iter);
+    }
+}
+
+impl<'a> FromIterator<Builder<'a>> for Batch<'a> {
+    fn from_iter<T: IntoIterator<Item = Builder<'a>>>(iter: T) -> Self {
+        let mut batch = Batch::new();
+        batch.extend(iter);
+        batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        assert_eq!(Batch::new().build(), "");
+    }
+
+    #[test]
+    fn defaults() {
+        let result = Batch::new()
+            .defaults(|b| b.name("xyz".to_string()).height_auto().inline(true))
+            .push(from_bytes(Vec::new()))
+            .push(from_bytes(Vec::new()).name("abc".to_string()).inline(false))
+            .build();
+        assert_eq!(
+            result,
+            concat!(
+                "\x1b]1337;File=size=0;name=eHl6;height=auto;inline=1:\u{0007}",
+                "\x1b]1337;File=size=0;name=YWJj;height=auto;inline=0:\u{0007}",
+            )
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn build_parallel() {
+        let batch = || {
+            (0..50u8)
+                .map(|i| from_bytes(vec![i; usize::from(i) * 100]))
+                .collect::<Batch>()
+                .defaults(|b| b.inline(true))
+        };
+        assert_eq!(batch().build_parallel(), batch().build());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_to() {
+        let batch = || {
+            Batch::new()
+                .defaults(|b| b.width(10))
+                .push(from_bytes("abc".as_bytes()))
+                .push(from_bytes("xyz".as_bytes()))
+        };
+        let mut buf = Vec::new();
+        batch().write_to(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), batch().build());
+    }
+
+    #[test]
+    fn from_iter() {
+        let batch: Batch = ["abc", "xyz"]
+            .iter()
+            .map(|s| from_bytes(s.as_bytes()))
+            .collect();
+        assert_eq!(
+            batch.build(),
+            "\x1b]1337;File=size=3:YWJj\u{0007}\x1b]1337;File=size=3:eHl6\u{0007}"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use image::{DynamicImage, Pixel, Rgba, RgbaImage};
+
+use crate::{Builder, Error};
+
+/// style of the caption drawn by [`Builder::caption`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptionStyle {
+    /// height of the text in pixels, rounded down to a multiple of 8
+    pub font_size: u32,
+    /// text color in RGBA
+    pub color: [u8; 4],
+    /// bar color in RGBA, blended over the image
+    pub background: [u8; 4],
+}
+
+/// white 16px text on a translucent black bar
+impl Default for CaptionStyle {
+    fn default() -> Self {
+        CaptionStyle {
+            font_size: 16,
+            color: [255, 255, 255, 255],
+            background: [0, 0, 0, 192],
+        }
+    }
+}
+
+impl Builder<'_> {
+    /// draws `text` in a bar at the bottom of the image
+    ///
+    /// The text is drawn with an embedded 8x8 bitmap font scaled to `style.font_size`,
+    /// or smaller so that the bar fits in the image,
+    /// and is cut off at the right edge. Characters other than printable ASCII are drawn as `?`.
+    /// The image is re-encoded as [`resize`](Builder::resize) does.
+    pub fn caption(self, text: &str, style: CaptionStyle) -> Result<Builder<'static>, Error> {
+        self.map_image(|image| {
+            let mut image = image.into_rgba8();
+            draw_caption(&mut image, text, &style);
+            Ok(DynamicImage::ImageRgba8(image))
+        })
+    }
+}
+
+fn draw_caption(image: &mut RgbaImage, text: &str, style: &CaptionStyle) {
+    let (width, height) = image.dimensions();
+    // the bar is 12 glyph pixels high including padding, and is kept within the image
+    let scale = (style.font_size / GLYPH_SIZE)
+        .min(height / (GLYPH_SIZE + 4))
+        .max(1);
+    let padding = scale * 2;
+    let bar_height = GLYPH_SIZE * scale + padding * 2;
+    let top = height.saturating_sub(bar_height);
+
+    let background = Rgba(style.background);
+    for y in top..height {
+        for x in 0..width {
+            image.get_pixel_mut(x, y).blend(&background);
+        }
+    }
+
+    let color = Rgba(style.color);
+    let glyph_top = top + padding;
+    for (i, c) in text.chars().enumerate() {
+        let left = i as u64 * u64::from(GLYPH_SIZE * scale) + u64::from(padding);
+        if left >= u64::from(width) {
+            break;
+        }
+        let left = left as u32;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_SIZE {
+                if bits & (1 << column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = left.saturating_add(column * scale + dx);
+                        let y = glyph_top + row as u32 * scale + dy;
+                        if x < width && y < height {
+                            image.get_pixel_mut(x, y).blend(&color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn glyph(c: char) -> &'static [u8; 8] {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &GLYPHS[index]
+}
+
+const GLYPH_SIZE: u32 = 8;
+
+/// printable ASCII glyphs, one byte per row with the least significant bit on the left
+///
+/// From the public domain font8x8 by Daniel Hepper.
+#[rustfmt::skip]
+const GLYPHS: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x18, 0x3c, 0x3c, 0x18, 0x18, 0x00, 0x18, 0x00], // '!'
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x36, 0x36, 0x7f, 0x36, 0x7f, 0x36, 0x36, 0x00], // '#'
+    [0x0c, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x0c, 0x00], // '$'
+    [0x00, 0x63, 0x33, 0x18, 0x0c, 0x66, 0x63, 0x00], // '%'
+    [0x1c, 0x36, 0x1c, 0x6e, 0x3b, 0x33, 0x6e, 0x00], // '&'
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // "'"
+    [0x18, 0x0c, 0x06, 0x06, 0x06, 0x0c, 0x18, 0x00], // '('
+    [0x06, 0x0c, 0x18, 0x18, 0x18, 0x0c, 0x06, 0x00], // ')'
+    [0x00, 0x66, 0x3c, 0xff, 0x3c, 0x66, 0x00, 0x00], // '*'
+    [0x00, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ','
+    [0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00], // '.'
+    [0x60, 0x30, 0x18, 0x0c, 0x06, 0x03, 0x01, 0x00], // '/'
+    [0x3e, 0x63, 0x73, 0x7b, 0x6f, 0x67, 0x3e, 0x00], // '0'
+    [0x0c, 0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x3f, 0x00], // '1'
+    [0x1e, 0x33, 0x30, 0x1c, 0x06, 0x33, 0x3f, 0x00], // '2'
+    [0x1e, 0x33, 0x30, 0x1c, 0x30, 0x33, 0x1e, 0x00], // '3'
+    [0x38, 0x3c, 0x36, 0x33, 0x7f, 0x30, 0x78, 0x00], // '4'
+    [0x3f, 0x03, 0x1f, 0x30, 0x30, 0x33, 0x1e, 0x00], // '5'
+    [0x1c, 0x06, 0x03, 0x1f, 0x33, 0x33, 0x1e, 0x00], // '6'
+    [0x3f, 0x33, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x00], // '7'
+    [0x1e, 0x33, 0x33, 0x1e, 0x33, 0x33, 0x1e, 0x00], // '8'
+    [0x1e, 0x33, 0x33, 0x3e, 0x30, 0x18, 0x0e, 0x00], // '9'
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x00], // ':'
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ';'
+    [0x18, 0x0c, 0x06, 0x03, 0x06, 0x0c, 0x18, 0x00], // '<'
+    [0x00, 0x00, 0x3f, 0x00, 0x00, 0x3f, 0x00, 0x00], // '='
+    [0x06, 0x0c, 0x18, 0x30, 0x18, 0x0c, 0x06, 0x00], // '>'
+    [0x1e, 0x33, 0x30, 0x18, 0x0c, 0x00, 0x0c, 0x00], // '?'
+    [0x3e, 0x63, 0x7b, 0x7b, 0x7b, 0x03, 0x1e, 0x00], // '@'
+    [0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00], // 'A'
+    [0x3f, 0x66, 0x66, 0x3e, 0x66, 0x66, 0x3f, 0x00], // 'B'
+    [0x3c, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3c, 0x00], // 'C'
+    [0x1f, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1f, 0x00], // 'D'
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x46, 0x7f, 0x00], // 'E'
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x06, 0x0f, 0x00], // 'F'
+    [0x3c, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7c, 0x00], // 'G'
+    [0x33, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x33, 0x00], // 'H'
+    [0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'I'
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e, 0x00], // 'J'
+    [0x67, 0x66, 0x36, 0x1e, 0x36, 0x66, 0x67, 0x00], // 'K'
+    [0x0f, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7f, 0x00], // 'L'
+    [0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63, 0x63, 0x00], // 'M'
+    [0x63, 0x67, 0x6f, 0x7b, 0x73, 0x63, 0x63, 0x00], // 'N'
+    [0x1c, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1c, 0x00], // 'O'
+    [0x3f, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00], // 'P'
+    [0x1e, 0x33, 0x33, 0x33, 0x3b, 0x1e, 0x38, 0x00], // 'Q'
+    [0x3f, 0x66, 0x66, 0x3e, 0x36, 0x66, 0x67, 0x00], // 'R'
+    [0x1e, 0x33, 0x07, 0x0e, 0x38, 0x33, 0x1e, 0x00], // 'S'
+    [0x3f, 0x2d, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'T'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3f, 0x00], // 'U'
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 'V'
+    [0x63, 0x63, 0x63, 0x6b, 0x7f, 0x77, 0x63, 0x00], // 'W'
+    [0x63, 0x63, 0x36, 0x1c, 0x1c, 0x36, 0x63, 0x00], // 'X'
+    [0x33, 0x33, 0x33, 0x1e, 0x0c, 0x0c, 0x1e, 0x00], // 'Y'
+    [0x7f, 0x63, 0x31, 0x18, 0x4c, 0x66, 0x7f, 0x00], // 'Z'
+    [0x1e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1e, 0x00], // '['
+    [0x03, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x40, 0x00], // '\\'
+    [0x1e, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1e, 0x00], // ']'
+    [0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff], // '_'
+    [0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x6e, 0x00], // 'a'
+    [0x07, 0x06, 0x06, 0x3e, 0x66, 0x66, 0x3b, 0x00], // 'b'
+    [0x00, 0x00, 0x1e, 0x33, 0x03, 0x33, 0x1e, 0x00], // 'c'
+    [0x38, 0x30, 0x30, 0x3e, 0x33, 0x33, 0x6e, 0x00], // 'd'
+    [0x00, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // 'e'
+    [0x1c, 0x36, 0x06, 0x0f, 0x06, 0x06, 0x0f, 0x00], // 'f'
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 'g'
+    [0x07, 0x06, 0x36, 0x6e, 0x66, 0x66, 0x67, 0x00], // 'h'
+    [0x0c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'i'
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e], // 'j'
+    [0x07, 0x06, 0x66, 0x36, 0x1e, 0x36, 0x67, 0x00], // 'k'
+    [0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 'l'
+    [0x00, 0x00, 0x33, 0x7f, 0x7f, 0x6b, 0x63, 0x00], // 'm'
+    [0x00, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x33, 0x00], // 'n'
+    [0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1e, 0x00], // 'o'
+    [0x00, 0x00, 0x3b, 0x66, 0x66, 0x3e, 0x06, 0x0f], // 'p'
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x78], // 'q'
+    [0x00, 0x00, 0x3b, 0x6e, 0x66, 0x06, 0x0f, 0x00], // 'r'
+    [0x00, 0x00, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x00], // 's'
+    [0x08, 0x0c, 0x3e, 0x0c, 0x0c, 0x2c, 0x18, 0x00], // 't'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6e, 0x00], // 'u'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 'v'
+    [0x00, 0x00, 0x63, 0x6b, 0x7f, 0x7f, 0x36, 0x00], // 'w'
+    [0x00, 0x00, 0x63, 0x36, 0x1c, 0x36, 0x63, 0x00], // 'x'
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 'y'
+    [0x00, 0x00, 0x3f, 0x19, 0x0c, 0x26, 0x3f, 0x00], // 'z'
+    [0x38, 0x0c, 0x0c, 0x07, 0x0c, 0x0c, 0x38, 0x00], // '{'
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // '|'
+    [0x07, 0x0c, 0x0c, 0x38, 0x0c, 0x0c, 0x07, 0x00], // '}'
+    [0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_bytes;
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn style() -> CaptionStyle {
+        CaptionStyle {
+            font_size: 8,
+            color: [255, 255, 255, 255],
+            background: [0, 0, 0, 255],
+        }
+    }
+
+    #[test]
+    fn draw_caption() {
+        let mut image = RgbaImage::from_pixel(20, 20, Rgba([255, 0, 0, 255]));
+        super::draw_caption(&mut image, "A", &style());
+        // bar of 8 + 2 * 2 pixels at the bottom
+        assert_eq!(*image.get_pixel(0, 7), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(0, 8), BLACK);
+        // first row of 'A' is 0x0c, drawn from (2, 10)
+        assert_eq!(*image.get_pixel(3, 10), BLACK);
+        assert_eq!(*image.get_pixel(4, 10), WHITE);
+        assert_eq!(*image.get_pixel(5, 10), WHITE);
+        assert_eq!(*image.get_pixel(6, 10), BLACK);
+    }
+
+    #[test]
+    fn draw_caption_scaled() {
+        let style = CaptionStyle {
+            font_size: 20,
+            ..style()
+        };
+        let mut image = RgbaImage::from_pixel(40, 40, Rgba([255, 0, 0, 255]));
+        super::draw_caption(&mut image, "A", &style);
+        // scale 2, bar of 16 + 4 * 2 pixels
+        assert_eq!(*image.get_pixel(0, 15), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(0, 16), BLACK);
+        assert_eq!(*image.get_pixel(8, 20), WHITE);
+        assert_eq!(*image.get_pixel(11, 21), WHITE);
+        assert_eq!(*image.get_pixel(12, 20), BLACK);
+    }
+
+    #[test]
+    fn draw_caption_clipped() {
+        let mut image = RgbaImage::from_pixel(10, 4, Rgba([255, 0, 0, 255]));
+        super::draw_caption(
+            &mut image,
+            "long caption \u{1f600}",
+            &CaptionStyle::default(),
+        );
+        assert!(image.pixels().all(|p| p.0[0] < 255 || *p == WHITE));
+    }
+
+    #[test]
+    fn draw_caption_font_larger_than_image() {
+        let style = CaptionStyle {
+            font_size: u32::MAX,
+            ..style()
+        };
+        let mut image = RgbaImage::from_pixel(24, 24, Rgba([255, 0, 0, 255]));
+        super::draw_caption(&mut image, &"x".repeat(1000), &style);
+        // scale 2, the bar covering the whole image
+        assert_eq!(*image.get_pixel(0, 0), BLACK);
+        assert_eq!(*image.get_pixel(23, 23), BLACK);
+
+        let mut image = RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]));
+        super::draw_caption(&mut image, "x", &style);
+    }
+
+    #[test]
+    fn glyph() {
+        assert_eq!(
+            super::glyph('A'),
+            &[0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00]
+        );
+        assert_eq!(super::glyph('\u{e9}'), super::glyph('?'));
+    }
+
+    #[test]
+    fn caption() {
+        let data = crate::png::encode_rgba(16, 16, &[0; 16 * 16 * 4]);
+        let result = from_bytes(data)
+            .inline(true)
+            .caption("hi", style())
+            .unwrap();
+        let image = result.decode_image().unwrap();
+        assert_eq!((image.width(), image.height()), (16, 16));
+        assert_eq!(result.get_inline(), Some(true));
+    }
+}
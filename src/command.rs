@@ -0,0 +1,49 @@
+use std::fmt;
+
+use crate::Builder;
+
+/// crossterm command that displays an inline image
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Write;
+///
+/// let mut out = Vec::new();
+/// let image = iterm2img::from_bytes("abc".as_bytes()).width(5);
+/// crossterm::queue!(out, iterm2img::DisplayImage(image)).unwrap();
+/// out.flush().unwrap();
+///
+/// assert_eq!(out, b"\x1b]1337;File=size=3;width=5:YWJj\x07");
+/// ```
+pub struct DisplayImage<'a>(pub Builder<'a>);
+
+impl crossterm::Command for DisplayImage<'_> {
+    fn write_ansi(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+
+    #[cfg(windows)]
+    fn execute_winapi(&self) -> std::io::Result<()> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "inline images are not supported by the WinAPI",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_bytes;
+
+    #[test]
+    fn write_ansi() {
+        let builder = from_bytes("abcdefg".as_bytes()).name("xyz".to_string());
+        let expected = builder.to_string();
+
+        let mut s = String::new();
+        crossterm::Command::write_ansi(&DisplayImage(builder), &mut s).unwrap();
+        assert_eq!(s, expected);
+    }
+}
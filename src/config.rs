@@ -0,0 +1,116 @@
+use alloc::vec::Vec;
+
+use crate::{from_bytes, Builder, LengthUnit, Passthrough, Terminator};
+
+/// application-wide display policy
+///
+/// Unlike [`Options`](crate::Options), the config only fills in options that the builder does not set.
+///
+/// # Examples
+///
+/// ```
+/// let config = iterm2img::DisplayConfig {
+///     width: Some(iterm2img::LengthUnit::Percent(50)),
+///     inline: Some(true),
+///     terminator: Some(iterm2img::Terminator::St),
+///     ..Default::default()
+/// };
+///
+/// let result = iterm2img::from_bytes_with("abc".as_bytes(), &config).build();
+/// assert_eq!(result, "\x1b]1337;File=size=3;width=50%;inline=1:YWJj\x1b\\");
+///
+/// let result = iterm2img::from_bytes_with("abc".as_bytes(), &config)
+///     .width(10)
+///     .build();
+/// assert_eq!(result, "\x1b]1337;File=size=3;width=10;inline=1:YWJj\x1b\\");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisplayConfig {
+    /// width
+    pub width: Option<LengthUnit>,
+    /// height
+    pub height: Option<LengthUnit>,
+    /// inline
+    pub inline: Option<bool>,
+    /// OSC terminator
+    pub terminator: Option<Terminator>,
+    /// terminal multiplexer passthrough
+    pub passthrough: Option<Passthrough>,
+}
+
+impl DisplayConfig {
+    /// returns empty config
+    pub fn new() -> DisplayConfig {
+        DisplayConfig::default()
+    }
+
+    /// set the config to the builder, keeping the options already set
+    pub fn apply<'a>(&self, mut builder: Builder<'a>) -> Builder<'a> {
+        builder.width = builder.width.or(self.width);
+        builder.height = builder.height.or(self.height);
+        builder.inline = builder.inline.or(self.inline);
+        builder.terminator = builder.terminator.or(self.terminator);
+        builder.passthrough = builder.passthrough.or(self.passthrough);
+        builder
+    }
+}
+
+/// returns builder from bytes with the config applied
+///
+/// Options set on the returned builder override the config.
+pub fn from_bytes_with(bytes: impl Into<Vec<u8>>, config: &DisplayConfig) -> Builder<'static> {
+    config.apply(from_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes_with() {
+        let config = DisplayConfig {
+            height: Some(LengthUnit::Cell(5)),
+            passthrough: Some(Passthrough::Tmux),
+            ..DisplayConfig::new()
+        };
+        assert_eq!(
+            super::from_bytes_with("abc".as_bytes(), &config).build(),
+            "\x1bPtmux;\x1b\x1b]1337;File=size=3;height=5:YWJj\u{0007}\x1b\\"
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let config = DisplayConfig {
+            width: Some(LengthUnit::Cell(5)),
+            inline: Some(false),
+            ..DisplayConfig::new()
+        };
+        let builder = from_bytes("abc".as_bytes()).width(10).inline(true);
+        assert_eq!(
+            config.apply(builder).build(),
+            "\x1b]1337;File=size=3;width=10;inline=1:YWJj\u{0007}"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let config = DisplayConfig {
+            width: Some(LengthUnit::Auto),
+            terminator: Some(Terminator::St),
+            passthrough: Some(Passthrough::Screen),
+            ..DisplayConfig::new()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(
+            json,
+            r#"{"width":"auto","height":null,"inline":null,"terminator":"st","passthrough":"screen"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<DisplayConfig>(&json).unwrap(),
+            config
+        );
+    }
+}
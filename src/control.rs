@@ -0,0 +1,357 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use std::{io, path::Path};
+
+use crate::{framing::Framer, to_base64_str, Error, Passthrough, Terminator};
+
+/// builder for iTerm2 control sequences other than images
+///
+/// # Examples
+///
+/// ```
+/// let result = iterm2img::set_background_image_file("/tmp/a.png").build();
+/// assert_eq!(result, "\x1b]1337;SetBackgroundImageFile=L3RtcC9hLnBuZw==\u{0007}");
+/// ```
+pub struct Control {
+    parts: Vec<Part>,
+    terminator: Option<Terminator>,
+    passthrough: Option<Passthrough>,
+}
+
+/// kind of attention requested by [`request_attention`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attention {
+    /// bounce the dock icon until the terminal is activated
+    Yes,
+    /// cancel a previous request
+    No,
+    /// bounce the dock icon once
+    Once,
+    /// show fireworks at the cursor
+    Fireworks,
+}
+
+impl Attention {
+    fn as_str(self) -> &'static str {
+        match self {
+            Attention::Yes => "yes",
+            Attention::No => "no",
+            Attention::Once => "once",
+            Attention::Fireworks => "fireworks",
+        }
+    }
+}
+
+/// cell size in points reported by the terminal
+///
+/// Parsed from the reply to [`report_cell_size`].
+///
+/// # Examples
+///
+/// ```
+/// let size = iterm2img::CellSize::parse("\x1b]1337;ReportCellSize=17.0;8.0;2.0\x1b\\")?;
+/// assert_eq!(size.width, 8.0);
+/// assert_eq!(size.height, 17.0);
+/// assert_eq!(size.scale, 2.0);
+/// # Ok::<(), iterm2img::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellSize {
+    /// width in points
+    pub width: f64,
+    /// height in points
+    pub height: f64,
+    /// number of pixels per point, 1.0 if not reported
+    pub scale: f64,
+}
+
+impl CellSize {
+    /// parses reply `ESC ]1337;ReportCellSize=height;width;scale ST`
+    ///
+    /// Both BEL and ST terminators are accepted, and `scale` may be omitted.
+    pub fn parse(reply: &str) -> Result<CellSize, Error> {
+        let body = reply
+            .strip_prefix("\x1b]1337;ReportCellSize=")
+            .and_then(|s| {
+                s.strip_suffix("\x1b\\")
+                    .or_else(|| s.strip_suffix('\u{0007}'))
+            })
+            .ok_or(Error::InvalidResponse)?;
+        let values = body
+            .split(';')
+            .map(|v| v.parse::<f64>().map_err(|_| Error::InvalidResponse))
+            .collect::<Result<Vec<_>, _>>()?;
+        match values[..] {
+            [height, width] => Ok(CellSize {
+                width,
+                height,
+                scale: 1.0,
+            }),
+            [height, width, scale] => Ok(CellSize {
+                width,
+                height,
+                scale,
+            }),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+}
+
+enum Part {
+    /// `ESC ]1337;` and the terminator are added around
+    Osc(String),
+    /// emitted as is
+    Text(String),
+}
+
+/// returns control sequence setting the background image to the file
+///
+/// The path is base64-encoded as the protocol specifies.
+#[cfg(feature = "std")]
+pub fn set_background_image_file(path: impl AsRef<Path>) -> Control {
+    let path = to_base64_str(path.as_ref().as_os_str().as_encoded_bytes());
+    Control::new(vec![Part::Osc(format!("SetBackgroundImageFile={}", path))])
+}
+
+/// returns control sequence clearing the background image
+pub fn clear_background_image() -> Control {
+    Control::new(vec![Part::Osc("SetBackgroundImageFile=".to_string())])
+}
+
+/// returns control sequence copying the text to the clipboard
+///
+/// The text is sent between `CopyToClipboard` and `EndCopy`.
+/// Control characters, line breaks included, are removed so that the text cannot end the copy early.
+pub fn copy_to_clipboard(text: impl Into<String>) -> Control {
+    Control::new(vec![
+        Part::Osc("CopyToClipboard=".to_string()),
+        Part::Text(strip_control(&text.into())),
+        Part::Osc("EndCopy".to_string()),
+    ])
+}
+
+/// returns control sequence setting the user-defined variable
+///
+/// The value is base64-encoded, and can be shown in badges and the status bar as `user.<name>`.
+/// Control characters are removed from the name so that they cannot end the sequence early.
+pub fn set_user_var(name: &str, value: &str) -> Control {
+    let value = to_base64_str(value.as_bytes());
+    let body = format!("SetUserVar={}={}", strip_control(name), value);
+    Control::new(vec![Part::Osc(body)])
+}
+
+/// returns control sequence reporting the user and host name for shell integration
+///
+/// Control characters are removed so that they cannot end the sequence early.
+pub fn remote_host(user: &str, host: &str) -> Control {
+    let body = format!("RemoteHost={}@{}", user, host);
+    Control::new(vec![Part::Osc(strip_control(&body))])
+}
+
+/// returns control sequence reporting the current directory for shell integration
+///
+/// Control characters are removed so that they cannot end the sequence early.
+#[cfg(feature = "std")]
+pub fn current_dir(path: impl AsRef<Path>) -> Control {
+    let body = format!("CurrentDir={}", path.as_ref().to_string_lossy());
+    Control::new(vec![Part::Osc(strip_control(&body))])
+}
+
+/// returns control sequence requesting the user's attention
+pub fn request_attention(v: Attention) -> Control {
+    Control::new(vec![Part::Osc(format!("RequestAttention={}", v.as_str()))])
+}
+
+/// returns control sequence requesting the cell size
+///
+/// The terminal replies with the sequence that [`CellSize::parse`] parses.
+pub fn report_cell_size() -> Control {
+    Control::new(vec![Part::Osc("ReportCellSize".to_string())])
+}
+
+impl Control {
+    fn new(parts: Vec<Part>) -> Control {
+        Control {
+            parts,
+            terminator: None,
+            passthrough: None,
+        }
+    }
+
+    /// set OSC terminator
+    pub fn terminator(mut self, v: Terminator) -> Control {
+        self.terminator = Some(v);
+        self
+    }
+
+    /// set terminal multiplexer passthrough
+    pub fn passthrough(mut self, v: Passthrough) -> Control {
+        self.passthrough = Some(v);
+        self
+    }
+
+    /// build string
+    pub fn build(&self) -> String {
+        String::from_utf8(self.build_bytes()).expect("sequence is valid UTF-8")
+    }
+
+    /// build bytes
+    pub fn build_bytes(&self) -> Vec<u8> {
+        let passthrough = self.passthrough.unwrap_or_default();
+        let terminator = match passthrough {
+            Passthrough::Screen => Terminator::Bel,
+            _ => self.terminator.unwrap_or_default(),
+        };
+        let mut framer = Framer::new(passthrough, None);
+        let mut out = Vec::new();
+        framer.start(&mut out);
+        for part in &self.parts {
+            match part {
+                Part::Osc(body) => {
+                    framer.push(b"\x1b]1337;", &mut out);
+                    framer.push(body.as_bytes(), &mut out);
+                    framer.push(terminator.as_str().as_bytes(), &mut out);
+                }
+                Part::Text(text) => framer.push(text.as_bytes(), &mut out),
+            }
+        }
+        framer.finish(&mut out);
+        out
+    }
+
+    /// write sequence to writer
+    #[cfg(feature = "std")]
+    pub fn write_to(&self, w: &mut impl io::Write) -> io::Result<()> {
+        w.write_all(&self.build_bytes())
+    }
+}
+
+fn strip_control(s: &str) -> String {
+    s.chars().filter(|c| !c.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn background_image_file() {
+        let result = set_background_image_file("/tmp/a.png").build();
+        assert_eq!(
+            result,
+            "\x1b]1337;SetBackgroundImageFile=L3RtcC9hLnBuZw==\u{0007}"
+        );
+
+        let result = clear_background_image().terminator(Terminator::St).build();
+        assert_eq!(result, "\x1b]1337;SetBackgroundImageFile=\x1b\\");
+    }
+
+    #[test]
+    fn clipboard() {
+        let result = copy_to_clipboard("abc").build();
+        assert_eq!(
+            result,
+            "\x1b]1337;CopyToClipboard=\u{0007}abc\x1b]1337;EndCopy\u{0007}"
+        );
+
+        let result = copy_to_clipboard("a\x1b]1337;EndCopy\x07\u{9c}\u{9b}b").build();
+        assert_eq!(
+            result,
+            "\x1b]1337;CopyToClipboard=\u{0007}a]1337;EndCopyb\x1b]1337;EndCopy\u{0007}"
+        );
+
+        let result = copy_to_clipboard("abc")
+            .passthrough(Passthrough::Tmux)
+            .build();
+        assert_eq!(
+            result,
+            "\x1bPtmux;\x1b\x1b]1337;CopyToClipboard=\u{0007}abc\x1b\x1b]1337;EndCopy\u{0007}\x1b\\"
+        );
+    }
+
+    #[test]
+    fn user_var() {
+        let result = set_user_var("status", "done").build();
+        assert_eq!(result, "\x1b]1337;SetUserVar=status=ZG9uZQ==\u{0007}");
+
+        let result = set_user_var("a\u{0007}\x1b]0;title\u{0007}b", "done").build();
+        assert_eq!(result, "\x1b]1337;SetUserVar=a]0;titleb=ZG9uZQ==\u{0007}");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shell_integration() {
+        let result = remote_host("user", "example.com").build();
+        assert_eq!(result, "\x1b]1337;RemoteHost=user@example.com\u{0007}");
+
+        let result = current_dir("/home/user").build();
+        assert_eq!(result, "\x1b]1337;CurrentDir=/home/user\u{0007}");
+
+        let result = current_dir("/tmp/a\x07\x1b]b").build();
+        assert_eq!(result, "\x1b]1337;CurrentDir=/tmp/a]b\u{0007}");
+    }
+
+    #[test]
+    fn attention() {
+        let result = request_attention(Attention::Yes).build();
+        assert_eq!(result, "\x1b]1337;RequestAttention=yes\u{0007}");
+
+        let result = request_attention(Attention::Fireworks).build();
+        assert_eq!(result, "\x1b]1337;RequestAttention=fireworks\u{0007}");
+    }
+
+    #[test]
+    fn cell_size() {
+        let result = report_cell_size().build();
+        assert_eq!(result, "\x1b]1337;ReportCellSize\u{0007}");
+
+        let size = CellSize::parse("\x1b]1337;ReportCellSize=17.5;8\u{0007}").unwrap();
+        assert_eq!(
+            size,
+            CellSize {
+                width: 8.0,
+                height: 17.5,
+                scale: 1.0
+            }
+        );
+
+        for reply in [
+            "",
+            "\x1b]1337;ReportCellSize=17;8",
+            "\x1b]1337;ReportCellSize=17\u{0007}",
+            "\x1b]1337;ReportCellSize=17;8;2;1\u{0007}",
+            "\x1b]1337;ReportCellSize=a;8\u{0007}",
+        ] {
+            assert!(matches!(
+                CellSize::parse(reply),
+                Err(Error::InvalidResponse)
+            ));
+        }
+    }
+
+    #[test]
+    fn passthrough() {
+        let result = clear_background_image()
+            .passthrough(Passthrough::Tmux)
+            .build();
+        assert_eq!(
+            result,
+            "\x1bPtmux;\x1b\x1b]1337;SetBackgroundImageFile=\u{0007}\x1b\\"
+        );
+
+        let result = clear_background_image()
+            .passthrough(Passthrough::Screen)
+            .terminator(Terminator::St)
+            .build();
+        assert_eq!(
+            result,
+            "\x1bP\x1b]1337;SetBackgroundImageFile=\u{0007}\x1b\\"
+        );
+    }
+}
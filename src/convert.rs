@@ -0,0 +1,156 @@
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use crate::{from_bytes, from_slice, Builder, Error};
+
+/// values that can be displayed as an inline image
+///
+/// # Examples
+///
+/// ```
+/// use iterm2img::ToInlineImage;
+///
+/// fn show(v: &impl ToInlineImage) -> Result<String, iterm2img::Error> {
+///     Ok(v.to_inline_image()?.inline(true).build())
+/// }
+///
+/// let result = show(&b"abc".to_vec())?;
+/// assert_eq!(result, "\x1b]1337;File=size=3;inline=1:YWJj\u{0007}");
+/// # Ok::<(), iterm2img::Error>(())
+/// ```
+pub trait ToInlineImage {
+    /// returns builder of the value
+    ///
+    /// Fails if the value cannot be read or encoded.
+    fn to_inline_image(&self) -> Result<Builder<'_>, Error>;
+}
+
+/// the bytes are borrowed, not copied
+impl ToInlineImage for [u8] {
+    fn to_inline_image(&self) -> Result<Builder<'_>, Error> {
+        Ok(from_slice(self))
+    }
+}
+
+/// the bytes are borrowed, not copied
+impl ToInlineImage for Vec<u8> {
+    fn to_inline_image(&self) -> Result<Builder<'_>, Error> {
+        Ok(from_slice(self))
+    }
+}
+
+/// the file is read as [`from_path`](crate::from_path) does
+#[cfg(feature = "std")]
+impl ToInlineImage for Path {
+    fn to_inline_image(&self) -> Result<Builder<'_>, Error> {
+        crate::from_path(self)
+    }
+}
+
+/// the file is read as [`from_path`](crate::from_path) does
+#[cfg(feature = "std")]
+impl ToInlineImage for PathBuf {
+    fn to_inline_image(&self) -> Result<Builder<'_>, Error> {
+        crate::from_path(self)
+    }
+}
+
+/// the image is encoded as PNG
+#[cfg(feature = "image")]
+impl ToInlineImage for image::DynamicImage {
+    fn to_inline_image(&self) -> Result<Builder<'_>, Error> {
+        crate::from_image(self)
+    }
+}
+
+impl From<Vec<u8>> for Builder<'static> {
+    fn from(bytes: Vec<u8>) -> Self {
+        from_bytes(bytes)
+    }
+}
+
+/// the bytes are borrowed, not copied
+impl<'a> From<&'a [u8]> for Builder<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        from_slice(bytes)
+    }
+}
+
+/// the file is read as [`from_path`](crate::from_path) does
+#[cfg(feature = "std")]
+impl TryFrom<&Path> for Builder<'static> {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        crate::from_path(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes() {
+        let expected = "\x1b]1337;File=size=3:YWJj\u{0007}";
+        assert_eq!(b"abc"[..].to_inline_image().unwrap().build(), expected);
+        assert_eq!(b"abc".to_vec().to_inline_image().unwrap().build(), expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn path() {
+        let path = std::env::temp_dir().join("iterm2img_to_inline_image.png");
+        std::fs::write(&path, "abc").unwrap();
+        let result = path.to_inline_image().unwrap().build();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;name=aXRlcm0yaW1nX3RvX2lubGluZV9pbWFnZS5wbmc=;inline=1:YWJj\u{0007}"
+        );
+
+        let result = Path::new("/nonexistent/a.png").to_inline_image();
+        assert!(matches!(result, Err(Error::ReadFile { .. })));
+    }
+
+    #[test]
+    fn from() {
+        let expected = "\x1b]1337;File=size=3:YWJj\u{0007}";
+        assert_eq!(Builder::from(b"abc".to_vec()).build(), expected);
+        assert_eq!(Builder::from(&b"abc"[..]).build(), expected);
+
+        let batch: crate::Batch = [&b"abc"[..], &b"xyz"[..]]
+            .into_iter()
+            .map(Builder::from)
+            .collect();
+        assert_eq!(
+            batch.build(),
+            "\x1b]1337;File=size=3:YWJj\u{0007}\x1b]1337;File=size=3:eHl6\u{0007}"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn try_from_path() {
+        let path = std::env::temp_dir().join("iterm2img_try_from_path.txt");
+        std::fs::write(&path, "abc").unwrap();
+        let result = Builder::try_from(path.as_path()).unwrap().build();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;name=aXRlcm0yaW1nX3RyeV9mcm9tX3BhdGgudHh0:YWJj\u{0007}"
+        );
+
+        let result = Builder::try_from(Path::new("/nonexistent/a.png"));
+        assert!(matches!(result, Err(Error::ReadFile { .. })));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn dynamic_image() {
+        let image = image::DynamicImage::new_rgba8(1, 1);
+        let result = image.to_inline_image().unwrap();
+        assert_eq!(result.build(), crate::from_image(&image).unwrap().build());
+    }
+}
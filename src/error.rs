@@ -0,0 +1,166 @@
+use alloc::string::String;
+use core::{error, fmt};
+#[cfg(feature = "std")]
+use std::{io, path::PathBuf};
+
+/// error
+///
+/// Variants depend on the enabled features, so more may be added.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// io error
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// failed to read file
+    #[cfg(feature = "std")]
+    ReadFile { path: PathBuf, source: io::Error },
+    /// pixel buffer length does not match the dimensions
+    InvalidPixelLength { expected: usize, actual: usize },
+    /// malformed base64 data
+    InvalidBase64(base64::DecodeError),
+    /// malformed `data:` URI
+    InvalidDataUri,
+    /// image size is zero or too large
+    InvalidImageSize { width: u32, height: u32 },
+    /// raw filename contains characters that break the sequence
+    InvalidName(String),
+    /// download without a filename
+    MissingName,
+    /// width or height is not valid
+    InvalidDimension(crate::LengthUnit),
+    /// malformed width or height string
+    InvalidLength(String),
+    /// payload exceeds the configured limit
+    PayloadTooLarge { len: usize, limit: usize },
+    /// malformed reply from the terminal
+    InvalidResponse,
+    /// malformed inline image sequence
+    InvalidSequence,
+    /// no supported terminal is detected
+    UnsupportedTerminal,
+    /// backend cannot display the payload format, `None` if the format is not known
+    UnsupportedFormat(Option<crate::FileFormat>),
+    /// backend cannot encode the input
+    EncodeFailure(&'static str),
+    /// http request error
+    #[cfg(feature = "http")]
+    Http(Box<ureq::Error>),
+    /// image encoding error
+    #[cfg(feature = "image")]
+    Image(image::ImageError),
+    /// svg parsing error
+    #[cfg(feature = "svg")]
+    Svg(resvg::usvg::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(e) => write!(f, "io error: {}", e),
+            #[cfg(feature = "std")]
+            Error::ReadFile { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            Error::InvalidPixelLength { expected, actual } => write!(
+                f,
+                "invalid pixel length: expected {} bytes, got {}",
+                expected, actual
+            ),
+            Error::InvalidBase64(e) => write!(f, "invalid base64: {}", e),
+            Error::InvalidDataUri => write!(f, "invalid data uri"),
+            Error::InvalidImageSize { width, height } => {
+                write!(f, "invalid image size: {}x{}", width, height)
+            }
+            Error::InvalidName(name) => write!(f, "invalid name: {:?}", name),
+            Error::MissingName => write!(f, "download requires a name"),
+            Error::InvalidDimension(v) => write!(f, "invalid dimension: {}", v),
+            Error::InvalidLength(s) => write!(f, "invalid length: {:?}", s),
+            Error::PayloadTooLarge { len, limit } => {
+                write!(f, "payload too large: {} bytes, limit {}", len, limit)
+            }
+            Error::InvalidResponse => write!(f, "invalid response"),
+            Error::InvalidSequence => write!(f, "invalid sequence"),
+            Error::UnsupportedTerminal => write!(f, "unsupported terminal"),
+            Error::UnsupportedFormat(Some(format)) => write!(f, "unsupported format: {}", format),
+            Error::UnsupportedFormat(None) => write!(f, "unknown format"),
+            Error::EncodeFailure(reason) => write!(f, "failed to encode: {}", reason),
+            #[cfg(feature = "http")]
+            Error::Http(e) => write!(f, "http error: {}", e),
+            #[cfg(feature = "image")]
+            Error::Image(e) => write!(f, "image error: {}", e),
+            #[cfg(feature = "svg")]
+            Error::Svg(e) => write!(f, "svg error: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(e) => Some(e),
+            #[cfg(feature = "std")]
+            Error::ReadFile { source, .. } => Some(source),
+            Error::InvalidPixelLength { .. } => None,
+            #[cfg(feature = "std")]
+            Error::InvalidBase64(e) => Some(e),
+            // `DecodeError` implements `Error` only with base64's `std` feature
+            #[cfg(not(feature = "std"))]
+            Error::InvalidBase64(_) => None,
+            Error::InvalidDataUri => None,
+            Error::InvalidImageSize { .. } => None,
+            Error::InvalidName(_) => None,
+            Error::MissingName => None,
+            Error::InvalidDimension(_) => None,
+            Error::InvalidLength(_) => None,
+            Error::PayloadTooLarge { .. } => None,
+            Error::InvalidResponse => None,
+            Error::InvalidSequence => None,
+            Error::UnsupportedTerminal => None,
+            Error::UnsupportedFormat(_) => None,
+            Error::EncodeFailure(_) => None,
+            #[cfg(feature = "http")]
+            Error::Http(e) => Some(e.as_ref()),
+            #[cfg(feature = "image")]
+            Error::Image(e) => Some(e),
+            #[cfg(feature = "svg")]
+            Error::Svg(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(e: base64::DecodeError) -> Self {
+        Error::InvalidBase64(e)
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<ureq::Error> for Error {
+    fn from(e: ureq::Error) -> Self {
+        Error::Http(Box::new(e))
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+#[cfg(feature = "svg")]
+impl From<resvg::usvg::Error> for Error {
+    fn from(e: resvg::usvg::Error) -> Self {
+        Error::Svg(e)
+    }
+}
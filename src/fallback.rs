@@ -0,0 +1,243 @@
+use std::fmt::Write;
+
+use image::{imageops::FilterType, DynamicImage, Rgba};
+
+/// number of columns used when neither width nor height is given in cells
+const DEFAULT_COLUMNS: u64 = 80;
+
+/// largest number of columns and rows, to bound the resized image
+const MAX_CELLS: u64 = 1024;
+
+/// brightness from which Braille dots are lit
+pub(crate) const DEFAULT_THRESHOLD: u8 = 128;
+
+/// bits of the Braille dots, indexed by row and column of the 2x4 cell
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// returns columns and rows of the text art of `image`, each cell showing `cell` pixels
+///
+/// A cell is assumed to be twice as tall as wide, so the aspect ratio is kept for the missing side.
+/// Without both sides, the image is shown at one pixel per dot, narrowed down to `DEFAULT_COLUMNS` columns.
+/// Both are clamped to `MAX_CELLS`.
+pub(crate) fn grid(
+    image: &DynamicImage,
+    columns: Option<u64>,
+    rows: Option<u64>,
+    cell: (u32, u32),
+) -> (u32, u32) {
+    let (w, h) = (u64::from(image.width()), u64::from(image.height()));
+    let (cw, ch) = (u64::from(cell.0), u64::from(cell.1));
+    // clamped first so that the products below cannot overflow
+    let columns = columns.map(|c| c.min(MAX_CELLS));
+    let rows = rows.map(|r| r.min(MAX_CELLS));
+    let (columns, rows) = match (columns, rows) {
+        (Some(c), Some(r)) => (c, r),
+        (Some(c), None) => (c, (h * c * cw).div_ceil(w * ch)),
+        (None, Some(r)) => ((w * r * ch).div_ceil(h * cw), r),
+        (None, None) => {
+            let c = w.div_ceil(cw).min(DEFAULT_COLUMNS);
+            (c, (h * c * cw).div_ceil(w * ch))
+        }
+    };
+    let fit = |v: u64| v.clamp(1, MAX_CELLS) as u32;
+    (fit(columns), fit(rows))
+}
+
+/// renders `image` as `columns` x `rows` cells of truecolor `▀`/`▄` half blocks
+///
+/// Transparent pixels are left as the default background. Rows are separated by `line_break`.
+pub(crate) fn half_blocks(
+    image: &DynamicImage,
+    columns: u32,
+    rows: u32,
+    line_break: &str,
+) -> String {
+    let image = image
+        .resize_exact(columns, rows.saturating_mul(2), FilterType::Triangle)
+        .into_rgba8();
+    let mut s = String::new();
+    for y in 0..rows {
+        if y > 0 {
+            s.push_str(line_break);
+        }
+        let mut last = String::new();
+        for x in 0..columns {
+            let top = opaque(image.get_pixel(x, y * 2));
+            let bottom = opaque(image.get_pixel(x, y * 2 + 1));
+            let (sgr, c) = match (top, bottom) {
+                (Some(t), Some(b)) => (format!("38;2;{};48;2;{}", t, b), '▀'),
+                (Some(t), None) => (format!("49;38;2;{}", t), '▀'),
+                (None, Some(b)) => (format!("49;38;2;{}", b), '▄'),
+                (None, None) => ("49".to_string(), ' '),
+            };
+            if sgr != last {
+                let _ = write!(s, "\x1b[{}m", sgr);
+                last = sgr;
+            }
+            s.push(c);
+        }
+        s.push_str("\x1b[0m");
+    }
+    s
+}
+
+/// renders `image` as `columns` x `rows` cells of Braille patterns
+///
+/// Dots are lit for pixels at least as bright as `threshold`, and transparent pixels are never lit.
+/// With `dither`, the error is spread to the neighbouring dots with Floyd-Steinberg dithering.
+pub(crate) fn braille(
+    image: &DynamicImage,
+    columns: u32,
+    rows: u32,
+    threshold: u8,
+    dither: bool,
+    line_break: &str,
+) -> String {
+    let image = image
+        .resize_exact(
+            columns.saturating_mul(2),
+            rows.saturating_mul(4),
+            FilterType::Triangle,
+        )
+        .into_luma_alpha8();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut levels: Vec<f32> = image
+        .pixels()
+        .map(|p| {
+            let [l, a] = p.0;
+            f32::from(l) * f32::from(a) / 255.0
+        })
+        .collect();
+    let mut lit = vec![false; levels.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let on = levels[i] >= f32::from(threshold);
+            lit[i] = on;
+            if dither {
+                let error = levels[i] - if on { 255.0 } else { 0.0 };
+                let mut spread = |dx: isize, dy: usize, weight: f32| {
+                    let nx = x as isize + dx;
+                    if nx >= 0 && (nx as usize) < width && y + dy < height {
+                        levels[(y + dy) * width + nx as usize] += error * weight;
+                    }
+                };
+                spread(1, 0, 7.0 / 16.0);
+                spread(-1, 1, 3.0 / 16.0);
+                spread(0, 1, 5.0 / 16.0);
+                spread(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+    let mut s = String::new();
+    for row in 0..rows as usize {
+        if row > 0 {
+            s.push_str(line_break);
+        }
+        for column in 0..columns as usize {
+            let mut bits = 0;
+            for (dy, dots) in BRAILLE_DOTS.iter().enumerate() {
+                for (dx, bit) in dots.iter().enumerate() {
+                    if lit[(row * 4 + dy) * width + column * 2 + dx] {
+                        bits |= bit;
+                    }
+                }
+            }
+            s.push(char::from_u32(0x2800 + bits).unwrap_or(' '));
+        }
+    }
+    s
+}
+
+/// returns `r;g;b` of the pixel, or `None` if it is mostly transparent
+fn opaque(p: &Rgba<u8>) -> Option<String> {
+    let [r, g, b, a] = p.0;
+    (a >= 128).then(|| format!("{};{};{}", r, g, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn grid() {
+        let image = DynamicImage::new_rgb8(40, 20);
+        assert_eq!(super::grid(&image, Some(10), Some(3), (1, 2)), (10, 3));
+        assert_eq!(super::grid(&image, Some(10), None, (1, 2)), (10, 3));
+        assert_eq!(super::grid(&image, None, Some(5), (1, 2)), (20, 5));
+        assert_eq!(super::grid(&image, None, None, (1, 2)), (40, 10));
+        assert_eq!(super::grid(&image, None, None, (2, 4)), (20, 5));
+
+        let image = DynamicImage::new_rgb8(400, 100);
+        assert_eq!(super::grid(&image, None, None, (1, 2)), (80, 10));
+        assert_eq!(super::grid(&image, Some(0), Some(0), (1, 2)), (1, 1));
+
+        assert_eq!(
+            super::grid(&image, Some(u64::MAX / 2), None, (1, 2)),
+            (1024, 128)
+        );
+        assert_eq!(
+            super::grid(&image, Some(100_000), Some(100_000), (2, 4)),
+            (1024, 1024)
+        );
+        let image = DynamicImage::new_rgb8(1, 10000);
+        assert_eq!(super::grid(&image, Some(10), None, (1, 2)), (10, 1024));
+    }
+
+    #[test]
+    fn half_blocks() {
+        let red = Rgba([255, 0, 0, 255]);
+        let blue = Rgba([0, 0, 255, 255]);
+        let clear = Rgba([0, 0, 0, 0]);
+        let pixels = [[red, red, clear, clear], [blue, blue, blue, clear]];
+        let image = RgbaImage::from_fn(4, 2, |x, y| pixels[y as usize][x as usize]);
+        let result = super::half_blocks(&image.into(), 4, 1, "\n");
+        let expected = concat!(
+            "\x1b[38;2;255;0;0;48;2;0;0;255m▀▀",
+            "\x1b[49;38;2;0;0;255m▄",
+            "\x1b[49m ",
+            "\x1b[0m",
+        );
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn braille() {
+        let on = Rgba([255, 255, 255, 255]);
+        let off = Rgba([0, 0, 0, 255]);
+        let clear = Rgba([255, 255, 255, 0]);
+        let pixels = [
+            [on, off, off, on],
+            [off, on, off, on],
+            [off, off, on, clear],
+            [on, on, off, on],
+        ];
+        let image = RgbaImage::from_fn(4, 4, |x, y| pixels[y as usize][x as usize]);
+        let result = super::braille(&image.into(), 2, 1, 128, false, "\n");
+        assert_eq!(result, "\u{28d1}\u{289c}");
+    }
+
+    #[test]
+    fn braille_dither() {
+        let image = RgbaImage::from_pixel(4, 8, Rgba([128, 128, 128, 255])).into();
+        let result = super::braille(&image, 2, 2, 128, false, "\n");
+        assert_eq!(result, "\u{28ff}\u{28ff}\n\u{28ff}\u{28ff}");
+
+        let result = super::braille(&image, 2, 2, 128, true, "\n");
+        let lit: u32 = result
+            .chars()
+            .filter(|c| *c != '\n')
+            .map(|c| (c as u32 - 0x2800).count_ones())
+            .sum();
+        assert!((12..=20).contains(&lit), "{}", result);
+    }
+
+    #[test]
+    fn half_blocks_rows() {
+        let image = RgbaImage::from_pixel(1, 4, Rgba([1, 2, 3, 255]));
+        let result = super::half_blocks(&image.into(), 1, 2, "\r\n");
+        let row = "\x1b[38;2;1;2;3;48;2;1;2;3m▀\x1b[0m";
+        assert_eq!(result, format!("{}\r\n{}", row, row));
+    }
+}
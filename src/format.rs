@@ -0,0 +1,81 @@
+use core::fmt;
+
+/// file format sniffed from the magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// PNG
+    Png,
+    /// JPEG
+    Jpeg,
+    /// GIF, possibly animated
+    Gif,
+    /// WebP
+    WebP,
+    /// PDF
+    Pdf,
+    /// TIFF
+    Tiff,
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            FileFormat::Png => "PNG",
+            FileFormat::Jpeg => "JPEG",
+            FileFormat::Gif => "GIF",
+            FileFormat::WebP => "WebP",
+            FileFormat::Pdf => "PDF",
+            FileFormat::Tiff => "TIFF",
+        };
+        f.write_str(s)
+    }
+}
+
+/// number of leading bytes needed to detect any format
+pub(crate) const MAGIC_LEN: usize = 12;
+
+/// returns the format of the data from its magic bytes, or `None` if it is not known
+///
+/// # Examples
+///
+/// ```
+/// use iterm2img::{detect_format, FileFormat};
+///
+/// assert_eq!(detect_format(b"GIF89a\x01\x00\x01\x00"), Some(FileFormat::Gif));
+/// assert_eq!(detect_format(b"abc"), None);
+/// ```
+pub fn detect_format(data: &[u8]) -> Option<FileFormat> {
+    match data {
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n', ..] => Some(FileFormat::Png),
+        [0xff, 0xd8, 0xff, ..] => Some(FileFormat::Jpeg),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(FileFormat::Gif),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(FileFormat::WebP),
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some(FileFormat::Pdf),
+        [b'I', b'I', b'*', 0, ..] | [b'M', b'M', 0, b'*', ..] => Some(FileFormat::Tiff),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_format() {
+        let cases: [(&[u8], Option<FileFormat>); 10] = [
+            (b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR", Some(FileFormat::Png)),
+            (b"\xff\xd8\xff\xe0\0\x10JFIF", Some(FileFormat::Jpeg)),
+            (b"GIF87a", Some(FileFormat::Gif)),
+            (b"GIF89a", Some(FileFormat::Gif)),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", Some(FileFormat::WebP)),
+            (b"%PDF-1.7\n", Some(FileFormat::Pdf)),
+            (b"II*\0\x08\0\0\0", Some(FileFormat::Tiff)),
+            (b"MM\0*\0\0\0\x08", Some(FileFormat::Tiff)),
+            (b"RIFF\x24\0\0\0WAVEfmt ", None),
+            (b"\x89PN", None),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(super::detect_format(data), expected, "{:?}", data);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use alloc::vec::Vec;
+
+use crate::Passthrough;
+
+const ESC: u8 = 0x1b;
+
+/// maximum length of a DCS string that GNU screen passes through
+const SCREEN_CHUNK_LEN: usize = 768;
+
+/// wraps raw sequence bytes for terminal multiplexers
+pub(crate) struct Framer {
+    passthrough: Passthrough,
+    chunk_len: Option<usize>,
+    written: usize,
+}
+
+impl Framer {
+    /// `chunk_len` overrides the default chunk limit of the passthrough, and is ignored without passthrough
+    pub(crate) fn new(passthrough: Passthrough, chunk_len: Option<usize>) -> Framer {
+        let chunk_len = match passthrough {
+            Passthrough::None => None,
+            Passthrough::Tmux => chunk_len,
+            Passthrough::Screen => chunk_len.or(Some(SCREEN_CHUNK_LEN)),
+        };
+        Framer {
+            passthrough,
+            chunk_len,
+            written: 0,
+        }
+    }
+
+    /// returns whether the bytes are changed by framing
+    pub(crate) fn is_active(&self) -> bool {
+        self.passthrough != Passthrough::None
+    }
+
+    pub(crate) fn start(&mut self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.prefix());
+    }
+
+    /// `raw` must not end in the middle of a UTF-8 character
+    pub(crate) fn push(&mut self, raw: &[u8], out: &mut Vec<u8>) {
+        if self.passthrough == Passthrough::None {
+            out.extend_from_slice(raw);
+            return;
+        }
+
+        let mut rest = raw;
+        while !rest.is_empty() {
+            // ESC is doubled for tmux, and UTF-8 characters are never split into different chunks
+            let (unit, escaped) = match rest[0] {
+                ESC if self.passthrough == Passthrough::Tmux => (1, 2),
+                b => {
+                    let n = utf8_len(b).min(rest.len());
+                    (n, n)
+                }
+            };
+            if let Some(limit) = self.chunk_len {
+                if self.written > 0 && self.written + escaped > limit {
+                    out.extend_from_slice(self.suffix());
+                    out.extend_from_slice(self.prefix());
+                    self.written = 0;
+                }
+            }
+            if escaped > unit {
+                out.extend_from_slice(&[ESC, ESC]);
+            } else {
+                out.extend_from_slice(&rest[..unit]);
+            }
+            self.written += escaped;
+            rest = &rest[unit..];
+        }
+    }
+
+    pub(crate) fn finish(&mut self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.suffix());
+    }
+
+    /// returns length of `len` raw bytes containing `escapes` ESC once framed
+    ///
+    /// Exact unless the bytes are split into chunks, where a chunk may end early
+    /// to keep an escaped ESC or a UTF-8 character together.
+    pub(crate) fn framed_len(&self, len: usize, escapes: usize) -> usize {
+        let escaped = match self.passthrough {
+            Passthrough::None => return len,
+            Passthrough::Tmux => len + escapes,
+            Passthrough::Screen => len,
+        };
+        let chunks = self
+            .chunk_len
+            .map_or(1, |limit| escaped.div_ceil(limit.max(1)).max(1));
+        escaped + chunks * (self.prefix().len() + self.suffix().len())
+    }
+
+    fn prefix(&self) -> &'static [u8] {
+        match self.passthrough {
+            Passthrough::None => b"",
+            Passthrough::Tmux => b"\x1bPtmux;",
+            Passthrough::Screen => b"\x1bP",
+        }
+    }
+
+    fn suffix(&self) -> &'static [u8] {
+        match self.passthrough {
+            Passthrough::None => b"",
+            Passthrough::Tmux | Passthrough::Screen => b"\x1b\\",
+        }
+    }
+}
+
+fn utf8_len(lead: u8) -> usize {
+    match lead {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(passthrough: Passthrough, parts: &[&[u8]]) -> Vec<u8> {
+        frame_chunked(passthrough, None, parts)
+    }
+
+    fn frame_chunked(
+        passthrough: Passthrough,
+        chunk_len: Option<usize>,
+        parts: &[&[u8]],
+    ) -> Vec<u8> {
+        let mut framer = Framer::new(passthrough, chunk_len);
+        let mut out = Vec::new();
+        framer.start(&mut out);
+        for part in parts {
+            framer.push(part, &mut out);
+        }
+        framer.finish(&mut out);
+        out
+    }
+
+    #[test]
+    fn none() {
+        let result = frame(Passthrough::None, &[b"\x1b]abc", b"\x07"]);
+        assert_eq!(result, b"\x1b]abc\x07");
+    }
+
+    #[test]
+    fn tmux() {
+        let result = frame(Passthrough::Tmux, &[b"\x1b]abc", b"\x1b\\"]);
+        assert_eq!(result, b"\x1bPtmux;\x1b\x1b]abc\x1b\x1b\\\x1b\\");
+    }
+
+    #[test]
+    fn screen() {
+        let raw = vec![b'a'; SCREEN_CHUNK_LEN * 2 + 1];
+        let result = frame(Passthrough::Screen, &[b"\x1b]", &raw[2..], b"\x07"]);
+
+        let mut expected = Vec::new();
+        for chunk in [b"\x1b]", &raw[2..], b"\x07"]
+            .concat()
+            .chunks(SCREEN_CHUNK_LEN)
+        {
+            expected.extend_from_slice(b"\x1bP");
+            expected.extend_from_slice(chunk);
+            expected.extend_from_slice(b"\x1b\\");
+        }
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn screen_utf8() {
+        let mut raw = vec![b'a'; SCREEN_CHUNK_LEN - 1];
+        raw.extend_from_slice("é".as_bytes());
+        let result = frame(Passthrough::Screen, &[&raw]);
+
+        let mut expected = b"\x1bP".to_vec();
+        expected.extend_from_slice(&raw[..SCREEN_CHUNK_LEN - 1]);
+        expected.extend_from_slice("\x1b\\\x1bPé\x1b\\".as_bytes());
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn tmux_chunked() {
+        let result = frame_chunked(Passthrough::Tmux, Some(4), &[b"\x1b]abc", b"\x07"]);
+        assert_eq!(result, b"\x1bPtmux;\x1b\x1b]a\x1b\\\x1bPtmux;bc\x07\x1b\\");
+    }
+
+    #[test]
+    fn screen_chunk_len() {
+        let result = frame_chunked(Passthrough::Screen, Some(3), &[b"abcdefg"]);
+        assert_eq!(result, b"\x1bPabc\x1b\\\x1bPdef\x1b\\\x1bPg\x1b\\");
+    }
+
+    #[test]
+    fn framed_len() {
+        let cases: [(Passthrough, Option<usize>, &[u8]); 4] = [
+            (Passthrough::None, None, b"\x1b]abc\x07"),
+            (Passthrough::Tmux, None, b"\x1b]abc\x07"),
+            (Passthrough::Tmux, Some(4), b"\x1b]abc\x07"),
+            (Passthrough::Screen, Some(3), b"abcdefg"),
+        ];
+        for (passthrough, chunk_len, raw) in cases {
+            let escapes = raw.iter().filter(|&&b| b == ESC).count();
+            let framer = Framer::new(passthrough, chunk_len);
+            let expected = frame_chunked(passthrough, chunk_len, &[raw]).len();
+            assert_eq!(framer.framed_len(raw.len(), escapes), expected);
+        }
+    }
+
+    #[test]
+    fn none_ignores_chunk_len() {
+        let result = frame_chunked(Passthrough::None, Some(3), &[b"abcdefg"]);
+        assert_eq!(result, b"abcdefg");
+    }
+}
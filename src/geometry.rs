@@ -0,0 +1,133 @@
+//! Conversion between pixels and cells
+//!
+//! # Examples
+//!
+//! ```
+//! use iterm2img::geometry::{self, CellPixels, Rounding};
+//!
+//! let cell = CellPixels { width: 8.0, height: 17.0 };
+//! let (cols, rows) = geometry::to_cells(100, 50, cell, Rounding::Ceil);
+//! assert_eq!((cols, rows), (13, 3));
+//!
+//! let result = iterm2img::from_bytes("abc".as_bytes()).width(cols).height(rows).build();
+//! assert_eq!(result, "\x1b]1337;File=size=3;width=13;height=3:YWJj\u{0007}");
+//! ```
+
+use crate::CellSize;
+
+/// how a fractional number of cells is rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// round down, the image fits in the cells
+    Floor,
+    /// round up, the cells cover the whole image
+    #[default]
+    Ceil,
+    /// round to the nearest
+    Nearest,
+}
+
+/// size of a cell in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellPixels {
+    /// width in pixels
+    pub width: f64,
+    /// height in pixels
+    pub height: f64,
+}
+
+impl From<CellSize> for CellPixels {
+    fn from(size: CellSize) -> Self {
+        CellPixels {
+            width: size.width * size.scale,
+            height: size.height * size.scale,
+        }
+    }
+}
+
+/// returns number of cells covering `px` pixels, 0 if `cell_px` is not positive
+pub fn px_to_cells(px: u64, cell_px: f64, rounding: Rounding) -> u64 {
+    if cell_px <= 0.0 || !cell_px.is_finite() {
+        return 0;
+    }
+    // `f64::floor` and friends need `std`, casting truncates the non-negative value instead
+    let cells = px as f64 / cell_px;
+    match rounding {
+        Rounding::Floor => cells as u64,
+        Rounding::Ceil => {
+            let floor = cells as u64;
+            if (floor as f64) < cells {
+                floor.saturating_add(1)
+            } else {
+                floor
+            }
+        }
+        Rounding::Nearest => (cells + 0.5) as u64,
+    }
+}
+
+/// returns number of pixels of `cells` cells, rounded to the nearest
+pub fn cells_to_px(cells: u64, cell_px: f64) -> u64 {
+    if cell_px <= 0.0 || !cell_px.is_finite() {
+        return 0;
+    }
+    (cells as f64 * cell_px + 0.5) as u64
+}
+
+/// returns columns and rows covering the pixel size
+pub fn to_cells(width_px: u64, height_px: u64, cell: CellPixels, rounding: Rounding) -> (u64, u64) {
+    (
+        px_to_cells(width_px, cell.width, rounding),
+        px_to_cells(height_px, cell.height, rounding),
+    )
+}
+
+/// returns pixel size of the columns and rows
+pub fn to_pixels(cols: u64, rows: u64, cell: CellPixels) -> (u64, u64) {
+    (
+        cells_to_px(cols, cell.width),
+        cells_to_px(rows, cell.height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding() {
+        assert_eq!(px_to_cells(20, 8.0, Rounding::Floor), 2);
+        assert_eq!(px_to_cells(20, 8.0, Rounding::Ceil), 3);
+        assert_eq!(px_to_cells(20, 8.0, Rounding::Nearest), 3);
+        assert_eq!(px_to_cells(19, 8.0, Rounding::Nearest), 2);
+        assert_eq!(px_to_cells(16, 8.0, Rounding::Ceil), 2);
+        assert_eq!(px_to_cells(16, 0.0, Rounding::Ceil), 0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let cell = CellPixels {
+            width: 7.5,
+            height: 16.0,
+        };
+        assert_eq!(to_pixels(4, 2, cell), (30, 32));
+        assert_eq!(to_cells(30, 32, cell, Rounding::Floor), (4, 2));
+        assert_eq!(cells_to_px(3, -1.0), 0);
+    }
+
+    #[test]
+    fn from_cell_size() {
+        let size = CellSize {
+            width: 8.0,
+            height: 17.0,
+            scale: 2.0,
+        };
+        assert_eq!(
+            CellPixels::from(size),
+            CellPixels {
+                width: 16.0,
+                height: 34.0
+            }
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::{io, time::Duration};
+
+use image::{
+    codecs::gif::{GifDecoder, GifEncoder, Repeat},
+    imageops::{self, FilterType},
+    AnimationDecoder, Delay, Frame, ImageDecoder, RgbaImage,
+};
+
+use crate::{from_bytes, Builder, Error};
+
+/// information about an animated GIF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GifInfo {
+    /// width in pixels
+    pub width: u32,
+    /// height in pixels
+    pub height: u32,
+    /// number of frames
+    pub frame_count: usize,
+    /// total duration of one loop
+    pub duration: Duration,
+}
+
+/// returns information about the GIF, returning an error if the data is not a GIF
+pub fn gif_info(data: &[u8]) -> Result<GifInfo, Error> {
+    let decoder = GifDecoder::new(io::Cursor::new(data))?;
+    let (width, height) = decoder.dimensions();
+    let mut frame_count = 0;
+    let mut duration = Duration::ZERO;
+    for frame in decoder.into_frames() {
+        let frame = frame?;
+        frame_count += 1;
+        duration += Duration::from(frame.delay());
+    }
+    Ok(GifInfo {
+        width,
+        height,
+        frame_count,
+        duration,
+    })
+}
+
+/// returns whether the data is a GIF with more than one frame
+pub(crate) fn is_animated(data: &[u8]) -> bool {
+    GifDecoder::new(io::Cursor::new(data))
+        .map(|decoder| decoder.into_frames().take(2).count() > 1)
+        .unwrap_or(false)
+}
+
+/// returns builder from GIF
+///
+/// The data is validated as a GIF and `inline` is set to true.
+/// If `max_size` is given and the GIF is larger, the width and height in pixels are set to fit within it,
+/// keeping the aspect ratio, so that iTerm2 scales the animation down.
+pub fn from_gif(
+    data: impl Into<Vec<u8>>,
+    max_size: Option<(u32, u32)>,
+) -> Result<Builder<'static>, Error> {
+    let data = data.into();
+    let info = gif_info(&data)?;
+    let builder = from_bytes(data).inline(true);
+    let (max_width, max_height) = match max_size {
+        Some(max) if info.width > max.0 || info.height > max.1 => max,
+        _ => return Ok(builder),
+    };
+    let (width, height) = fit_within(info.width, info.height, max_width, max_height);
+    Ok(builder
+        .width_px(u64::from(width))
+        .height_px(u64::from(height)))
+}
+
+/// low bits of each color channel dropped to shrink the palette, from the lightest reduction
+const POSTERIZE_SHIFTS: [u32; 3] = [0, 2, 4];
+
+/// encoder speed, 1 being the best and slowest quantization and 30 the fastest
+const ENCODE_SPEED: i32 = 10;
+
+/// shrinks the GIF to at most `limit` bytes while keeping it animated
+///
+/// The frames are first capped to fit within `max_size`, keeping the aspect ratio.
+/// Then, until the GIF fits, the palette is reduced, every other frame is dropped with its delay
+/// added to the kept frame so the timing is unchanged, and the frames are downscaled by a quarter.
+/// The data is returned as is if it already fits both.
+/// Returns `Error::PayloadTooLarge` if even a 1x1 GIF does not fit.
+pub fn optimize_gif(
+    data: &[u8],
+    limit: usize,
+    max_size: Option<(u32, u32)>,
+) -> Result<Vec<u8>, Error> {
+    let decoder = GifDecoder::new(io::Cursor::new(data))?;
+    let (width, height) = decoder.dimensions();
+    let (mut width, mut height) = match max_size {
+        Some((w, h)) if width > w || height > h => fit_within(width, height, w, h),
+        _ if data.len() <= limit => return Ok(data.to_vec()),
+        _ => (width, height),
+    };
+    let frames = decoder
+        .into_frames()
+        .map(|frame| frame.map(|f| (delay_ms(f.delay()), f.into_buffer())))
+        .collect::<Result<Vec<_>, _>>()?;
+    loop {
+        let scaled = frames
+            .iter()
+            .map(|(delay, image)| {
+                let image = if image.dimensions() == (width, height) {
+                    image.clone()
+                } else {
+                    imageops::resize(image, width, height, FilterType::Triangle)
+                };
+                (*delay, image)
+            })
+            .collect::<Vec<_>>();
+        for step in [1, 2] {
+            if step > 1 && frames.len() < 4 {
+                break;
+            }
+            let kept = drop_frames(&scaled, step);
+            for shift in POSTERIZE_SHIFTS {
+                let encoded = encode_gif(&kept, shift)?;
+                if encoded.len() <= limit {
+                    return Ok(encoded);
+                }
+            }
+        }
+        if width <= 1 && height <= 1 {
+            return Err(Error::PayloadTooLarge {
+                len: data.len(),
+                limit,
+            });
+        }
+        width = (width * 3 / 4).max(1);
+        height = (height * 3 / 4).max(1);
+    }
+}
+
+fn fit_within(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let scale = f64::min(
+        f64::from(max_width) / f64::from(width),
+        f64::from(max_height) / f64::from(height),
+    );
+    let fit = |v: u32| (f64::from(v) * scale).floor().max(1.0) as u32;
+    (fit(width), fit(height))
+}
+
+fn delay_ms(delay: Delay) -> u32 {
+    let (numer, denom) = delay.numer_denom_ms();
+    numer / denom.max(1)
+}
+
+/// keeps every `step`-th frame, adding the delays of the dropped frames to it
+fn drop_frames(frames: &[(u32, RgbaImage)], step: usize) -> Vec<(u32, &RgbaImage)> {
+    frames
+        .chunks(step)
+        .map(|group| {
+            let delay = group.iter().map(|(ms, _)| ms).sum();
+            (delay, &group[0].1)
+        })
+        .collect()
+}
+
+fn encode_gif(frames: &[(u32, &RgbaImage)], shift: u32) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut data, ENCODE_SPEED);
+        encoder.set_repeat(Repeat::Infinite)?;
+        for (delay, image) in frames {
+            let mut image = (*image).clone();
+            if shift > 0 {
+                let mask = 0xff << shift;
+                for p in image.pixels_mut() {
+                    for c in &mut p.0[..3] {
+                        *c &= mask;
+                    }
+                }
+            }
+            let delay = Delay::from_numer_denom_ms(*delay, 1);
+            encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::gif::GifEncoder, Delay, Frame, RgbaImage};
+
+    fn gif(width: u32, height: u32, delays_ms: &[u32]) -> Vec<u8> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut data);
+            for ms in delays_ms {
+                let image = RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255]));
+                let delay = Delay::from_numer_denom_ms(*ms, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn animated() {
+        assert!(is_animated(&gif(1, 1, &[100, 100])));
+        assert!(!is_animated(&gif(1, 1, &[100])));
+        assert!(!is_animated(&crate::png::encode_rgba(1, 1, &[0; 4])));
+    }
+
+    #[test]
+    fn info() {
+        let info = gif_info(&gif(4, 2, &[100, 200, 300])).unwrap();
+        assert_eq!(
+            info,
+            GifInfo {
+                width: 4,
+                height: 2,
+                frame_count: 3,
+                duration: Duration::from_millis(600),
+            }
+        );
+
+        let png = crate::png::encode_rgba(1, 1, &[0; 4]);
+        assert!(matches!(gif_info(&png), Err(Error::Image(_))));
+    }
+
+    fn noise_gif(width: u32, height: u32, frames: u32) -> Vec<u8> {
+        let mut state = 1u32;
+        let mut data = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut data);
+            for _ in 0..frames {
+                let image = RgbaImage::from_fn(width, height, |_, _| {
+                    state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    let [r, g, b, _] = state.to_le_bytes();
+                    image::Rgba([r, g, b, 255])
+                });
+                let delay = Delay::from_numer_denom_ms(100, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn optimize() {
+        let data = noise_gif(32, 32, 4);
+        let limit = data.len() / 3;
+        let result = optimize_gif(&data, limit, None).unwrap();
+        assert!(result.len() <= limit);
+        let info = gif_info(&result).unwrap();
+        assert!(info.frame_count > 1);
+        assert_eq!(info.duration, Duration::from_millis(400));
+    }
+
+    #[test]
+    fn optimize_max_size() {
+        let data = gif(40, 20, &[100, 100]);
+        let result = optimize_gif(&data, data.len(), None).unwrap();
+        assert_eq!(result, data);
+
+        let result = optimize_gif(&data, data.len(), Some((10, 10))).unwrap();
+        let info = gif_info(&result).unwrap();
+        assert_eq!((info.width, info.height, info.frame_count), (10, 5, 2));
+    }
+
+    #[test]
+    fn optimize_too_small() {
+        let data = noise_gif(8, 8, 2);
+        let len = data.len();
+        let result = optimize_gif(&data, 10, None);
+        assert!(matches!(
+            result,
+            Err(Error::PayloadTooLarge { len: l, limit: 10 }) if l == len
+        ));
+    }
+
+    #[test]
+    fn builder() {
+        let data = gif(40, 20, &[100, 100]);
+        let len = data.len();
+
+        let result = from_gif(data.clone(), None).unwrap().build();
+        assert!(result.starts_with(&format!("\x1b]1337;File=size={};inline=1:R0lG", len)));
+
+        let result = from_gif(data.clone(), Some((40, 40))).unwrap().build();
+        assert!(result.starts_with(&format!("\x1b]1337;File=size={};inline=1:", len)));
+
+        let result = from_gif(data, Some((10, 10))).unwrap().build();
+        assert!(result.starts_with(&format!(
+            "\x1b]1337;File=size={};width=10px;height=5px;inline=1:",
+            len
+        )));
+    }
+}
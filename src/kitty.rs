@@ -0,0 +1,136 @@
+use alloc::{format, string::String, vec::Vec};
+
+/// maximum base64 characters per transmission chunk
+const CHUNK_LEN: usize = 4096;
+
+/// placeholder character that the terminal replaces with the image cell
+const PLACEHOLDER: char = '\u{10EEEE}';
+
+/// combining characters encoding row and column numbers of placeholders, from kitty's `rowcolumn-diacritics.txt`
+const DIACRITICS: &[u32] = &[
+    0x0305, 0x030D, 0x030E, 0x0310, 0x0312, 0x033D, 0x033E, 0x033F, 0x0346, 0x034A, 0x034B, 0x034C,
+    0x0350, 0x0351, 0x0352, 0x0357, 0x035B, 0x0363, 0x0364, 0x0365, 0x0366, 0x0367, 0x0368, 0x0369,
+    0x036A, 0x036B, 0x036C, 0x036D, 0x036E, 0x036F, 0x0483, 0x0484, 0x0485, 0x0486, 0x0487, 0x0592,
+    0x0593, 0x0594, 0x0595, 0x0597, 0x0598, 0x0599, 0x059C, 0x059D, 0x059E, 0x059F, 0x05A0, 0x05A1,
+    0x05A8, 0x05A9, 0x05AB, 0x05AC, 0x05AF, 0x05C4, 0x0610, 0x0611, 0x0612, 0x0613, 0x0614, 0x0615,
+    0x0616, 0x0617, 0x0657, 0x0658, 0x0659, 0x065A, 0x065B, 0x065D, 0x065E, 0x06D6, 0x06D7, 0x06D8,
+    0x06D9, 0x06DA, 0x06DB, 0x06DC, 0x06DF, 0x06E0, 0x06E1, 0x06E2, 0x06E4, 0x06E7, 0x06E8, 0x06EB,
+    0x06EC, 0x0730, 0x0732, 0x0733, 0x0735, 0x0736, 0x073A, 0x073D, 0x073F, 0x0740, 0x0741, 0x0743,
+    0x0745, 0x0747, 0x0749, 0x074A, 0x07EB, 0x07EC, 0x07ED, 0x07EE, 0x07EF, 0x07F0, 0x07F1, 0x07F3,
+    0x0816, 0x0817, 0x0818, 0x0819, 0x081B, 0x081C, 0x081D, 0x081E, 0x081F, 0x0820, 0x0821, 0x0822,
+    0x0823, 0x0825, 0x0826, 0x0827, 0x0829, 0x082A, 0x082B, 0x082C, 0x082D,
+];
+
+/// maximum number of rows and columns that placeholders can address
+pub(crate) const MAX_PLACEHOLDER_CELLS: u64 = DIACRITICS.len() as u64;
+
+/// options of a Kitty graphics transmission
+pub(crate) struct Transmission {
+    /// image id, only the lower 24 bits are used
+    pub(crate) id: u32,
+    /// columns and rows
+    pub(crate) cells: Option<(u64, u64)>,
+    /// whether a virtual placement displayed with Unicode placeholders is created
+    pub(crate) unicode_placeholder: bool,
+}
+
+/// encodes base64-encoded PNG into Kitty graphics protocol sequences, chunked as the protocol requires
+pub(crate) fn encode(encoded: &str, t: &Transmission) -> String {
+    let mut control = format!("a=T,f=100,q=2,i={}", t.id & 0xff_ffff);
+    if let Some((cols, rows)) = t.cells {
+        control.push_str(&format!(",c={},r={}", cols, rows));
+    }
+    if t.unicode_placeholder {
+        control.push_str(",U=1");
+    }
+
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(CHUNK_LEN).collect();
+    let mut s = String::with_capacity(encoded.len() + chunks.len() * 16 + control.len());
+    if chunks.is_empty() {
+        s.push_str(&format!("\x1b_G{};\x1b\\", control));
+    }
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = i32::from(i + 1 < chunks.len());
+        let chunk = core::str::from_utf8(chunk).expect("base64 is ASCII");
+        if i == 0 {
+            s.push_str(&format!("\x1b_G{},m={};{}\x1b\\", control, more, chunk));
+        } else {
+            s.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    s
+}
+
+/// returns rows of placeholder characters for the image, colored with the image id
+///
+/// Each row starts below the start of the previous one, so the image keeps its shape
+/// when the cursor is not at the first column.
+pub(crate) fn placeholders(id: u32, cols: u64, rows: u64) -> String {
+    let id = id & 0xff_ffff;
+    let mut s = format!(
+        "\x1b[38;2;{};{};{}m",
+        (id >> 16) & 0xff,
+        (id >> 8) & 0xff,
+        id & 0xff
+    );
+    let cols = cols.min(MAX_PLACEHOLDER_CELLS);
+    for row in 0..rows.min(MAX_PLACEHOLDER_CELLS) {
+        if row > 0 {
+            // IND moves down and scrolls at the bottom, then CUB moves back to the first column
+            s.push_str(&format!("\x1bD\x1b[{}D", cols));
+        }
+        for col in 0..cols {
+            s.push(PLACEHOLDER);
+            s.push(diacritic(row));
+            s.push(diacritic(col));
+        }
+    }
+    s.push_str("\x1b[39m");
+    s
+}
+
+fn diacritic(n: u64) -> char {
+    char::from_u32(DIACRITICS[n as usize]).expect("diacritics are valid characters")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_single_chunk() {
+        let t = Transmission {
+            id: 7,
+            cells: Some((3, 2)),
+            unicode_placeholder: true,
+        };
+        assert_eq!(
+            encode("YWJj", &t),
+            "\x1b_Ga=T,f=100,q=2,i=7,c=3,r=2,U=1,m=0;YWJj\x1b\\"
+        );
+    }
+
+    #[test]
+    fn encode_chunks() {
+        let t = Transmission {
+            id: 1,
+            cells: None,
+            unicode_placeholder: false,
+        };
+        let encoded = "A".repeat(CHUNK_LEN * 2 + 4);
+        let expected = format!(
+            "\x1b_Ga=T,f=100,q=2,i=1,m=1;{}\x1b\\\x1b_Gm=1;{}\x1b\\\x1b_Gm=0;AAAA\x1b\\",
+            "A".repeat(CHUNK_LEN),
+            "A".repeat(CHUNK_LEN)
+        );
+        assert_eq!(encode(&encoded, &t), expected);
+    }
+
+    #[test]
+    fn placeholder_rows() {
+        assert_eq!(
+            placeholders(0x010203, 2, 2),
+            "\x1b[38;2;1;2;3m\u{10EEEE}\u{0305}\u{0305}\u{10EEEE}\u{0305}\u{030D}\x1bD\x1b[2D\u{10EEEE}\u{030D}\u{0305}\u{10EEEE}\u{030D}\u{030D}\x1b[39m"
+        );
+    }
+}
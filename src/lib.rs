@@ -3,261 +3,2994 @@
 //! # Examples
 //!
 //! ```
-//! let bytes = "abcdefg".as_bytes().to_vec();
-//! let result = iterm2img::from_bytes(bytes)
-//!     .name("xyz".to_string())
+//! let result = iterm2img::from_bytes("abcdefg".as_bytes())
+//!     .name("xyz")
 //!     .width(100)
 //!     .height(200)
 //!     .preserve_aspect_ratio(false)
 //!     .inline(true)
 //!     .build();
 //!
-//! let expected =  "\x1b]1337;File=size=7;name=xyz;width=100;height=200;preserve_aspect_ratio=0;inline=1:YWJjZGVmZw==\u{0007}";
+//! let expected =  "\x1b]1337;File=size=7;name=eHl6;width=100;height=200;preserve_aspect_ratio=0;inline=1:YWJjZGVmZw==\u{0007}";
 //! assert_eq!(result, expected);
 //! ```
+//!
+//! # `no_std`
+//!
+//! Building the sequence only needs `alloc`.
+//! Disable the default `std` feature to build without the standard library;
+//! reading files, writing to `io::Write` and terminal detection are then unavailable.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+    borrow::Cow,
+    format,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
 
 use base64::Engine;
 
+mod batch;
+#[cfg(feature = "image")]
+mod caption;
+#[cfg(feature = "crossterm")]
+mod command;
+mod config;
+mod control;
+mod convert;
+mod error;
+#[cfg(feature = "image")]
+mod fallback;
+mod format;
+mod framing;
+pub mod geometry;
+#[cfg(feature = "image")]
+mod gif;
+mod kitty;
+mod metadata;
+#[cfg(feature = "std")]
+mod multipart;
+mod options;
+pub mod parse;
+mod png;
+pub mod prelude;
+mod sequence;
+#[cfg(feature = "sixel")]
+mod sixel;
+mod stream;
+#[cfg(feature = "svg")]
+mod svg;
+#[cfg(feature = "termwiz")]
+mod termwiz;
+#[cfg(feature = "image")]
+mod transform;
+#[cfg(feature = "ratatui")]
+mod widget;
+
+pub use batch::Batch;
+#[cfg(feature = "image")]
+pub use caption::CaptionStyle;
+#[cfg(feature = "crossterm")]
+pub use command::DisplayImage;
+pub use config::{from_bytes_with, DisplayConfig};
+pub use control::{
+    clear_background_image, copy_to_clipboard, remote_host, report_cell_size, request_attention,
+    set_user_var, Attention, CellSize, Control,
+};
+#[cfg(feature = "std")]
+pub use control::{current_dir, set_background_image_file};
+pub use convert::ToInlineImage;
+pub use error::Error;
+pub use format::{detect_format, FileFormat};
+#[cfg(feature = "image")]
+pub use gif::{from_gif, gif_info, optimize_gif, GifInfo};
+#[cfg(feature = "image")]
+pub use image::imageops::FilterType;
+/// embeds the image at compile time
+///
+/// # Examples
+///
+/// ```
+/// let image = iterm2img::iterm2img!("examples/image.jpg", width = 20, inline = true);
+///
+/// let expected = iterm2img::from_file("examples/image.jpg")?.width(20).inline(true);
+/// assert_eq!(image.build(), expected.build());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "macros")]
+pub use iterm2img_macros::iterm2img;
+#[cfg(feature = "std")]
+pub use multipart::{multipart, MultipartBuilder};
+pub use options::Options;
+pub use sequence::Sequence;
+pub use stream::Chunks;
+#[cfg(feature = "std")]
+pub use stream::SequenceReader;
+#[cfg(feature = "image")]
+pub use transform::{Background, Format};
+#[cfg(feature = "ratatui")]
+pub use widget::InlineImage;
+
+use framing::Framer;
+use stream::Pieces;
+
 /// builder
-pub struct Builder {
-    bytes: Vec<u8>,
-    name: Option<String>,
+#[derive(Clone, Debug)]
+pub struct Builder<'a> {
+    payload: Payload<'a>,
+    #[cfg(feature = "std")]
+    path: Option<PathBuf>,
+    name: Option<Vec<u8>>,
+    raw_name: Option<bool>,
     width: Option<LengthUnit>,
     height: Option<LengthUnit>,
     preserve_aspect_ratio: Option<bool>,
     inline: Option<bool>,
+    do_not_move_cursor: Option<bool>,
+    raw_mode: Option<bool>,
+    terminator: Option<Terminator>,
+    passthrough: Option<Passthrough>,
+    passthrough_chunk_len: Option<usize>,
+    max_payload_len: Option<usize>,
+    backend: Option<Backend>,
+    hyperlink: Option<String>,
+    image_id: Option<u32>,
+    unicode_placeholder: Option<bool>,
+    threshold: Option<u8>,
+    dither: Option<bool>,
+    strict: Option<bool>,
+    strip_metadata: Option<bool>,
+    quality: Option<u8>,
+    line_width: Option<usize>,
+    engine: Option<base64::engine::GeneralPurpose>,
+    size_hint: Option<bool>,
+    max_width: Option<u64>,
+    max_height: Option<u64>,
+    cell_pixels: Option<geometry::CellPixels>,
+}
+
+#[derive(Clone)]
+enum Payload<'a> {
+    Bytes(Cow<'a, [u8]>),
+    #[cfg(feature = "bytes")]
+    Shared(bytes::Bytes),
+    #[cfg(feature = "mmap")]
+    Mapped(std::sync::Arc<memmap2::Mmap>),
+    Buffer(Arc<dyn AsRef<[u8]> + Send + Sync>),
+    Base64 {
+        encoded: String,
+        decoded_len: usize,
+    },
+}
+
+/// only the length is shown, not to dump the whole payload
+impl fmt::Debug for Payload<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Payload::Bytes(_) => "Bytes",
+            #[cfg(feature = "bytes")]
+            Payload::Shared(_) => "Shared",
+            #[cfg(feature = "mmap")]
+            Payload::Mapped(_) => "Mapped",
+            Payload::Buffer(_) => "Buffer",
+            Payload::Base64 { .. } => "Base64",
+        };
+        f.debug_struct(kind).field("len", &self.len()).finish()
+    }
+}
+
+/// OSC terminator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Terminator {
+    /// BEL (`\x07`)
+    #[default]
+    Bel,
+    /// ST (`ESC \`)
+    St,
+}
+
+impl Terminator {
+    /// returns terminator string
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Terminator::Bel => "\u{0007}",
+            Terminator::St => "\x1b\\",
+        }
+    }
+}
+
+/// terminal multiplexer passthrough
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Passthrough {
+    /// no wrapping
+    #[default]
+    None,
+    /// wrap in `ESC Ptmux; ... ESC \`, requires `set -g allow-passthrough on`
+    Tmux,
+    /// wrap in `ESC P ... ESC \` split into chunks that GNU screen accepts
+    ///
+    /// The OSC is always terminated with BEL, since ST would end the wrapping early.
+    Screen,
+}
+
+/// graphics protocol used for output
+///
+/// Variants depend on the enabled features, so more may be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Backend {
+    /// iTerm2 Inline Images Protocol
+    #[default]
+    Iterm2,
+    /// Sixel graphics
+    ///
+    /// The payload is decoded as an image, and the iTerm2 sequence is emitted instead if decoding fails
+    /// or the image would be resized to more than 2^26 pixels.
+    /// Only pixel width and height are used, and other options are ignored.
+    #[cfg(feature = "sixel")]
+    Sixel,
+    /// Terminology inline media, as `tycat` emits
+    ///
+    /// Terminology loads the media from a file, so this requires a builder created from a file
+    /// whose path has no control characters, and both width and height in cells, up to 1024 each.
+    /// Otherwise the iTerm2 sequence is emitted instead.
+    Terminology,
+    /// Kitty graphics protocol
+    ///
+    /// The payload must be PNG. Width and height in cells are used as columns and rows.
+    Kitty,
+    /// truecolor `▀`/`▄` half-block text art for terminals without graphics support
+    ///
+    /// The payload is decoded as an image, and the iTerm2 sequence is emitted instead if decoding fails.
+    /// Width and height in cells are used as columns and rows, at most 1024 each,
+    /// and the text is never passed through.
+    #[cfg(feature = "image")]
+    HalfBlocks,
+    /// monochrome Braille pattern text art, 2x4 dots per cell, for terminals without graphics support
+    ///
+    /// Dots are lit where the image is at least as bright as the threshold, optionally with dithering.
+    /// Otherwise the same as `HalfBlocks`.
+    #[cfg(feature = "image")]
+    Braille,
+}
+
+/// formats decoded for the backends rendering the image by themselves
+#[cfg(feature = "image")]
+const DECODABLE_FORMATS: [FileFormat; 3] = [FileFormat::Png, FileFormat::Jpeg, FileFormat::Gif];
+
+/// `TERM_PROGRAM` values of terminals supporting the iTerm2 protocol
+#[cfg(feature = "std")]
+const ITERM2_TERM_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "mintty", "vscode"];
+
+impl Backend {
+    /// returns the backend supported by the current terminal, guessed from the environment
+    #[cfg(feature = "std")]
+    pub fn detect() -> Backend {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        Backend::detect_from(&term, &term_program)
+    }
+
+    /// returns the backend supported by the current terminal
+    ///
+    /// Unlike `detect`, returns an error if the environment does not indicate any supported terminal.
+    #[cfg(feature = "std")]
+    pub fn try_detect() -> Result<Backend, Error> {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        Backend::try_detect_from(&term, &term_program)
+    }
+
+    /// returns the backend supported by the current terminal, or half blocks if there is none
+    #[cfg(feature = "image")]
+    pub fn detect_with_fallback() -> Backend {
+        Backend::try_detect().unwrap_or(Backend::HalfBlocks)
+    }
+
+    /// returns whether the backend can display the format
+    pub fn supports(self, format: FileFormat) -> bool {
+        match self {
+            Backend::Iterm2 | Backend::Terminology => true,
+            Backend::Kitty => format == FileFormat::Png,
+            #[cfg(feature = "sixel")]
+            Backend::Sixel => DECODABLE_FORMATS.contains(&format),
+            #[cfg(feature = "image")]
+            Backend::HalfBlocks | Backend::Braille => DECODABLE_FORMATS.contains(&format),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn try_detect_from(term: &str, term_program: &str) -> Result<Backend, Error> {
+        let backend = Backend::detect_from(term, term_program);
+        if backend == Backend::Iterm2 && !ITERM2_TERM_PROGRAMS.contains(&term_program) {
+            return Err(Error::UnsupportedTerminal);
+        }
+        Ok(backend)
+    }
+
+    #[cfg(feature = "std")]
+    fn detect_from(term: &str, term_program: &str) -> Backend {
+        if ITERM2_TERM_PROGRAMS.contains(&term_program) {
+            return Backend::Iterm2;
+        }
+        if term.starts_with("terminology") {
+            return Backend::Terminology;
+        }
+        if term == "xterm-kitty" || term_program == "ghostty" {
+            return Backend::Kitty;
+        }
+        #[cfg(feature = "sixel")]
+        if ["foot", "mlterm", "contour"]
+            .iter()
+            .any(|t| term.starts_with(t))
+        {
+            return Backend::Sixel;
+        }
+        Backend::Iterm2
+    }
+}
+
+/// terminal emulator whose quirks are applied by [`Builder::profile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalProfile {
+    /// iTerm2, no quirks
+    Iterm2,
+    /// WezTerm, displays inline images only
+    WezTerm,
+    /// mintty, displays inline images only and expects BEL
+    Mintty,
+    /// Visual Studio Code (xterm.js), displays inline images only and limits payload to 20MB
+    VSCode,
+    /// Konsole, displays inline images only
+    Konsole,
+}
+
+impl TerminalProfile {
+    /// returns the profile of the current terminal, guessed from the environment
+    #[cfg(feature = "std")]
+    pub fn detect() -> Option<TerminalProfile> {
+        let term_program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        let konsole = std::env::var_os("KONSOLE_VERSION").is_some();
+        TerminalProfile::detect_from(&term_program, konsole)
+    }
+
+    #[cfg(feature = "std")]
+    fn detect_from(term_program: &str, konsole: bool) -> Option<TerminalProfile> {
+        match term_program {
+            "iTerm.app" => Some(TerminalProfile::Iterm2),
+            "WezTerm" => Some(TerminalProfile::WezTerm),
+            "mintty" => Some(TerminalProfile::Mintty),
+            "vscode" => Some(TerminalProfile::VSCode),
+            _ if konsole => Some(TerminalProfile::Konsole),
+            _ => None,
+        }
+    }
+
+    fn defaults(self) -> Builder<'static> {
+        let builder = from_bytes(Vec::new());
+        match self {
+            TerminalProfile::Iterm2 => builder,
+            TerminalProfile::WezTerm | TerminalProfile::Konsole => builder.inline(true),
+            TerminalProfile::Mintty => builder.inline(true).terminator(Terminator::Bel),
+            TerminalProfile::VSCode => builder.inline(true).max_payload_len(20_000_000),
+        }
+    }
 }
 
-enum LengthUnit {
+/// width or height of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LengthUnit {
+    /// character cells
     Cell(u64),
+    /// pixels
     Pixel(u64),
+    /// percent of the session width or height
     Percent(u64),
+    /// image's inherent size
     Auto,
 }
 
+/// width or height parsed from a string such as a command line flag
+///
+/// # Examples
+///
+/// ```
+/// let width: iterm2img::Dimension = "300px".parse()?;
+/// let height: iterm2img::Dimension = "auto".parse()?;
+///
+/// let result = iterm2img::from_bytes("abc".as_bytes())
+///     .width_dim(width)
+///     .height_dim(height)
+///     .build();
+/// assert_eq!(result, "\x1b]1337;File=size=3;width=300px;height=auto:YWJj\u{0007}");
+/// # Ok::<(), iterm2img::Error>(())
+/// ```
+pub type Dimension = LengthUnit;
+
+/// parses the protocol syntax: `N` cells, `Npx`, `N%` or `auto`
+impl core::str::FromStr for LengthUnit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |v: &str| v.parse().map_err(|_| Error::InvalidLength(s.to_string()));
+        if s == "auto" {
+            Ok(LengthUnit::Auto)
+        } else if let Some(v) = s.strip_suffix("px") {
+            Ok(LengthUnit::Pixel(number(v)?))
+        } else if let Some(v) = s.strip_suffix('%') {
+            Ok(LengthUnit::Percent(number(v)?))
+        } else {
+            Ok(LengthUnit::Cell(number(s)?))
+        }
+    }
+}
+
+/// formats in the protocol syntax, the inverse of `from_str`
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LengthUnit::Cell(v) => write!(f, "{}", v),
+            LengthUnit::Pixel(v) => write!(f, "{}px", v),
+            LengthUnit::Percent(v) => write!(f, "{}%", v),
+            LengthUnit::Auto => f.write_str("auto"),
+        }
+    }
+}
+
+/// width and height of the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dimensions {
+    /// width
+    pub width: LengthUnit,
+    /// height
+    pub height: LengthUnit,
+}
+
+impl Dimensions {
+    /// returns dimensions from width and height
+    pub fn new(width: LengthUnit, height: LengthUnit) -> Dimensions {
+        Dimensions { width, height }
+    }
+}
+
 /// returns builder from bytes
-pub fn from_bytes(bytes: Vec<u8>) -> Builder {
+pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Builder<'static> {
+    from_payload(Payload::Bytes(Cow::Owned(bytes.into())))
+}
+
+/// returns builder from slice
+///
+/// The slice is borrowed, not copied.
+pub fn from_slice(bytes: &[u8]) -> Builder<'_> {
+    from_payload(Payload::Bytes(Cow::Borrowed(bytes)))
+}
+
+/// returns builder from owned or borrowed bytes
+///
+/// Borrowed bytes are not copied.
+pub fn from_cow<'a>(bytes: impl Into<Cow<'a, [u8]>>) -> Builder<'a> {
+    from_payload(Payload::Bytes(bytes.into()))
+}
+
+/// returns builder from any byte storage
+///
+/// The storage is kept as is, so `Arc<[u8]>`, `bytes::Bytes` or buffers of other crates are used without copying.
+pub fn from_buffer(buffer: impl AsRef<[u8]> + Send + Sync + 'static) -> Builder<'static> {
+    from_payload(Payload::Buffer(Arc::new(buffer)))
+}
+
+/// returns builder from `bytes::Bytes`
+///
+/// The buffer is shared, not copied.
+#[cfg(feature = "bytes")]
+pub fn from_shared(bytes: bytes::Bytes) -> Builder<'static> {
+    from_payload(Payload::Shared(bytes))
+}
+
+/// returns builder from base64-encoded bytes
+///
+/// `encoded` is used as is without re-encoding, and `size` is set to `decoded_len`.
+pub fn from_base64(encoded: String, decoded_len: usize) -> Builder<'static> {
+    from_payload(Payload::Base64 {
+        encoded,
+        decoded_len,
+    })
+}
+
+fn from_payload(payload: Payload<'_>) -> Builder<'_> {
     Builder {
-        bytes,
+        payload,
+        #[cfg(feature = "std")]
+        path: None,
         name: None,
+        raw_name: None,
         width: None,
         height: None,
         preserve_aspect_ratio: None,
         inline: None,
+        do_not_move_cursor: None,
+        raw_mode: None,
+        terminator: None,
+        passthrough: None,
+        passthrough_chunk_len: None,
+        max_payload_len: None,
+        backend: None,
+        hyperlink: None,
+        image_id: None,
+        unicode_placeholder: None,
+        threshold: None,
+        dither: None,
+        strict: None,
+        strip_metadata: None,
+        quality: None,
+        line_width: None,
+        engine: None,
+        size_hint: None,
+        max_width: None,
+        max_height: None,
+        cell_pixels: None,
+    }
+}
+
+/// returns builder from `data:` URI
+///
+/// Standard base64 data is used as is without re-encoding.
+pub fn from_data_uri(uri: &str) -> Result<Builder<'static>, Error> {
+    let rest = uri.strip_prefix("data:").ok_or(Error::InvalidDataUri)?;
+    let (meta, data) = rest.split_once(',').ok_or(Error::InvalidDataUri)?;
+
+    if !meta.ends_with(";base64") {
+        return Ok(from_bytes(percent_decode(data)?));
+    }
+
+    if let Some(decoded_len) = standard_base64_decoded_len(data) {
+        return Ok(from_base64(data.to_string(), decoded_len));
+    }
+
+    let engine = base64::engine::GeneralPurpose::new(
+        &base64::alphabet::URL_SAFE,
+        base64::engine::GeneralPurposeConfig::new()
+            .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+    );
+    let bytes = engine.decode(data).map_err(|_| Error::InvalidDataUri)?;
+    Ok(from_bytes(bytes))
+}
+
+/// returns builder from file
+///
+/// `name` is set to the filename.
+#[cfg(feature = "std")]
+pub fn from_file(path: impl AsRef<Path>) -> io::Result<Builder<'static>> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+    let mut builder = from_bytes(bytes);
+    builder.name = file_name(path);
+    builder.path = std::path::absolute(path).ok();
+    Ok(builder)
+}
+
+/// returns builder from path
+///
+/// `name` is set to the filename, and `inline` is set to true for known image extensions.
+///
+/// With the `image` feature, images with known extensions are also rotated and flipped
+/// as their EXIF orientation tag says (see [`Builder::auto_orient`]).
+/// Use [`from_file`] to keep the file as is.
+#[cfg(feature = "std")]
+pub fn from_path(path: impl AsRef<Path>) -> Result<Builder<'static>, Error> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| Error::ReadFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut builder = from_bytes(bytes);
+    builder.name = file_name(path);
+    builder.path = std::path::absolute(path).ok();
+    if has_image_extension(path) {
+        builder.inline = Some(true);
+        #[cfg(feature = "image")]
+        if let Ok(Some(bytes)) = builder.oriented_payload() {
+            builder = builder.with_image_bytes(bytes);
+        }
+    }
+    Ok(builder)
+}
+
+/// returns builder from memory-mapped file
+///
+/// The file is mapped instead of being read into memory. `name` is set to the filename.
+///
+/// The file must not be modified while the builder is alive.
+#[cfg(feature = "mmap")]
+pub fn from_file_mmap(path: impl AsRef<Path>) -> io::Result<Builder<'static>> {
+    let path = path.as_ref();
+    let file = fs::File::open(path)?;
+    // SAFETY: the caller must ensure that the file is not modified while mapped
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    let mut builder = from_payload(Payload::Mapped(std::sync::Arc::new(mmap)));
+    builder.name = file_name(path);
+    builder.path = std::path::absolute(path).ok();
+    Ok(builder)
+}
+
+/// returns builder from reader
+///
+/// The reader is read to the end to determine `size`.
+/// Use [`Builder::write_from_reader`] to stream it without holding it in memory.
+#[cfg(feature = "std")]
+pub fn from_reader(mut r: impl Read) -> io::Result<Builder<'static>> {
+    let mut bytes = Vec::new();
+    r.read_to_end(&mut bytes)?;
+    Ok(from_bytes(bytes))
+}
+
+/// returns builder from stdin
+///
+/// Stdin is read to the end to determine `size`.
+#[cfg(feature = "std")]
+pub fn from_stdin() -> io::Result<Builder<'static>> {
+    from_reader(io::stdin().lock())
+}
+
+/// returns builder from async reader
+///
+/// The reader is read to the end to determine `size`.
+#[cfg(feature = "tokio")]
+pub async fn from_async_reader(r: impl tokio::io::AsyncRead) -> io::Result<Builder<'static>> {
+    use tokio::io::AsyncReadExt;
+
+    let mut bytes = Vec::new();
+    Box::pin(r).read_to_end(&mut bytes).await?;
+    Ok(from_bytes(bytes))
+}
+
+/// returns builder from file asynchronously
+///
+/// `name` is set to the filename.
+#[cfg(feature = "tokio")]
+pub async fn from_async_file(path: impl AsRef<Path>) -> io::Result<Builder<'static>> {
+    let path = path.as_ref();
+    let bytes = tokio::fs::read(path).await?;
+    let mut builder = from_bytes(bytes);
+    builder.name = file_name(path);
+    builder.path = std::path::absolute(path).ok();
+    Ok(builder)
+}
+
+/// returns builder from url
+///
+/// The payload is downloaded from `url` and `name` is set to the last path segment.
+#[cfg(feature = "http")]
+pub fn from_url(url: &str) -> Result<Builder<'static>, Error> {
+    let response = ureq::get(url).call()?;
+    let mut builder = from_reader(response.into_reader())?;
+    builder.name = url_file_name(url).map(String::into_bytes);
+    Ok(builder)
+}
+
+/// returns builder from raw RGBA pixels
+///
+/// The pixels are wrapped into a PNG payload. `pixels` must be `width * height * 4` bytes.
+/// Returns `Error::InvalidImageSize` if `width` or `height` is zero, which PNG does not allow.
+pub fn from_rgba(width: u32, height: u32, pixels: &[u8]) -> Result<Builder<'static>, Error> {
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidImageSize { width, height });
+    }
+    let expected = width as usize * height as usize * 4;
+    if pixels.len() != expected {
+        return Err(Error::InvalidPixelLength {
+            expected,
+            actual: pixels.len(),
+        });
     }
+    Ok(from_bytes(png::encode_rgba(width, height, pixels)))
 }
 
-impl Builder {
+/// returns builder from image
+///
+/// The image is encoded as PNG.
+#[cfg(feature = "image")]
+pub fn from_image(image: &image::DynamicImage) -> Result<Builder<'static>, Error> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut io::Cursor::new(&mut bytes), image::ImageFormat::Png)?;
+    Ok(from_bytes(bytes))
+}
+
+/// returns builder from svg
+///
+/// The svg is rasterized to `width` x `height` pixels and encoded as PNG.
+#[cfg(feature = "svg")]
+pub fn from_svg(svg: &[u8], width: u32, height: u32) -> Result<Builder<'static>, Error> {
+    Ok(from_bytes(svg::rasterize(svg, width, height)?))
+}
+
+impl Builder<'static> {
+    /// returns builder without payload
+    ///
+    /// Options can be configured before the image data exists, and the payload is set later by `bytes` or `payload`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let builder = iterm2img::Builder::new().width(5).inline(true);
+    ///
+    /// let result = builder.clone().bytes("abc").build();
+    /// assert_eq!(result, "\x1b]1337;File=size=3;width=5;inline=1:YWJj\u{0007}");
+    /// ```
+    pub fn new() -> Builder<'static> {
+        from_bytes(Vec::new())
+    }
+}
+
+impl Default for Builder<'static> {
+    fn default() -> Self {
+        Builder::new()
+    }
+}
+
+impl<'a> Builder<'a> {
+    /// set payload, keeping the options
+    pub fn bytes(self, v: impl Into<Vec<u8>>) -> Builder<'static> {
+        from_bytes(v).with_defaults(&self)
+    }
+
+    /// set payload borrowing the bytes, keeping the options
+    pub fn payload<'b>(self, v: &'b [u8]) -> Builder<'b> {
+        from_slice(v).with_defaults(&self)
+    }
+
+    /// returns builder owning the payload
+    ///
+    /// A borrowed payload is copied, so that the builder can outlive it.
+    pub fn into_owned(mut self) -> Builder<'static> {
+        let payload = core::mem::replace(&mut self.payload, Payload::Bytes(Cow::Borrowed(&[])));
+        let builder = from_payload(payload.into_owned()).with_defaults(&self);
+        #[cfg(feature = "std")]
+        let builder = Builder {
+            path: self.path,
+            ..builder
+        };
+        builder
+    }
+
+    /// set display options
+    ///
+    /// Options set in `v` override the options already set.
+    pub fn options(self, v: &Options) -> Builder<'a> {
+        v.apply(self)
+    }
+
     /// set filename
-    pub fn name(mut self, v: String) -> Builder {
-        self.name = Some(v);
+    ///
+    /// The filename is base64-encoded as the protocol specifies, unless `raw_name` is set to true.
+    pub fn name(mut self, v: impl Into<String>) -> Builder<'a> {
+        self.name = Some(v.into().into_bytes());
+        self
+    }
+
+    /// set filename from bytes
+    ///
+    /// The bytes are base64-encoded as is, so a filename that is not valid UTF-8 is kept intact.
+    pub fn name_bytes(mut self, v: impl Into<Vec<u8>>) -> Builder<'a> {
+        self.name = Some(v.into());
+        self
+    }
+
+    /// set filename from `OsStr` or `Path`
+    ///
+    /// The platform bytes are used without lossy conversion, see `name_bytes`.
+    #[cfg(feature = "std")]
+    pub fn name_os(self, v: impl AsRef<std::ffi::OsStr>) -> Builder<'a> {
+        self.name_bytes(v.as_ref().as_encoded_bytes())
+    }
+
+    /// set whether filename is emitted without base64 encoding
+    ///
+    /// `;`, `:`, `%` and control characters in a raw filename are percent-escaped,
+    /// so the filename cannot end the sequence early. `try_build` rejects `;`, `:` and control characters instead.
+    pub fn raw_name(mut self, v: bool) -> Builder<'a> {
+        self.raw_name = Some(v);
         self
     }
 
     /// set width cells
-    pub fn width(mut self, v: u64) -> Builder {
+    pub fn width(mut self, v: u64) -> Builder<'a> {
         self.width = Some(LengthUnit::Cell(v));
         self
     }
 
     /// set width pixels
-    pub fn width_px(mut self, v: u64) -> Builder {
+    pub fn width_px(mut self, v: u64) -> Builder<'a> {
         self.width = Some(LengthUnit::Pixel(v));
         self
     }
 
     /// set width percent
-    pub fn width_percent(mut self, v: u64) -> Builder {
+    pub fn width_percent(mut self, v: u64) -> Builder<'a> {
         self.width = Some(LengthUnit::Percent(v));
         self
     }
 
     /// set width auto
-    pub fn width_auto(mut self) -> Builder {
+    pub fn width_auto(mut self) -> Builder<'a> {
         self.width = Some(LengthUnit::Auto);
         self
     }
 
     /// set height cells
-    pub fn height(mut self, v: u64) -> Builder {
+    pub fn height(mut self, v: u64) -> Builder<'a> {
         self.height = Some(LengthUnit::Cell(v));
         self
     }
 
     /// set height pixels
-    pub fn height_px(mut self, v: u64) -> Builder {
+    pub fn height_px(mut self, v: u64) -> Builder<'a> {
         self.height = Some(LengthUnit::Pixel(v));
         self
     }
 
     /// set height percent
-    pub fn height_percent(mut self, v: u64) -> Builder {
+    pub fn height_percent(mut self, v: u64) -> Builder<'a> {
         self.height = Some(LengthUnit::Percent(v));
         self
     }
 
     /// set height auto
-    pub fn height_auto(mut self) -> Builder {
+    pub fn height_auto(mut self) -> Builder<'a> {
         self.height = Some(LengthUnit::Auto);
         self
     }
 
-    /// set preserve_aspect_ratio
-    pub fn preserve_aspect_ratio(mut self, v: bool) -> Builder {
-        self.preserve_aspect_ratio = Some(v);
+    /// set width
+    pub fn width_dim(mut self, v: Dimension) -> Builder<'a> {
+        self.width = Some(v);
         self
     }
 
-    /// set inline
-    pub fn inline(mut self, v: bool) -> Builder {
-        self.inline = Some(v);
+    /// set height
+    pub fn height_dim(mut self, v: Dimension) -> Builder<'a> {
+        self.height = Some(v);
         self
     }
 
-    /// build string
-    pub fn build(self) -> String {
-        let mut s = String::new();
-
-        s.push_str("\x1b]1337;File=");
-        s.push_str(format!("size={}", self.bytes.len()).as_str());
+    /// set width and height
+    pub fn size(mut self, v: Dimensions) -> Builder<'a> {
+        self.width = Some(v.width);
+        self.height = Some(v.height);
+        self
+    }
 
-        if let Some(name) = self.name {
-            s.push_str(format!(";name={}", name).as_str());
-        }
+    /// set width and height cells to fit the current terminal
+    ///
+    /// Leaves the size unchanged when no terminal is attached.
+    #[cfg(feature = "terminal_size")]
+    pub fn fit_to_terminal(self) -> Builder<'a> {
+        self.fit_to_terminal_with_margin(0, 0)
+    }
 
-        if let Some(width) = self.width {
-            match width {
-                LengthUnit::Cell(w) => s.push_str(format!(";width={}", w).as_str()),
-                LengthUnit::Pixel(w) => s.push_str(format!(";width={}px", w).as_str()),
-                LengthUnit::Percent(w) => s.push_str(format!(";width={}%", w).as_str()),
-                LengthUnit::Auto => s.push_str(";width=auto"),
+    /// set width and height cells to fit the current terminal, leaving the given cells free
+    ///
+    /// Leaves the size unchanged when no terminal is attached.
+    #[cfg(feature = "terminal_size")]
+    pub fn fit_to_terminal_with_margin(self, columns: u16, rows: u16) -> Builder<'a> {
+        match terminal_size::terminal_size() {
+            Some((terminal_size::Width(w), terminal_size::Height(h))) => {
+                self.fit_to(w.saturating_sub(columns), h.saturating_sub(rows))
             }
+            None => self,
         }
+    }
 
-        if let Some(height) = self.height {
-            match height {
-                LengthUnit::Cell(h) => s.push_str(format!(";height={}", h).as_str()),
-                LengthUnit::Pixel(h) => s.push_str(format!(";height={}px", h).as_str()),
-                LengthUnit::Percent(h) => s.push_str(format!(";height={}%", h).as_str()),
-                LengthUnit::Auto => s.push_str(";height=auto"),
-            }
-        }
+    /// set maximum width cells
+    ///
+    /// A width in cells larger than this is capped.
+    /// A width in pixels is capped too if it covers more cells with the size set by [`Builder::cell_pixels`].
+    /// Other widths, including none, are left as is, as the displayed size is not known.
+    pub fn max_width(mut self, v: u64) -> Builder<'a> {
+        self.max_width = Some(v);
+        self
+    }
 
-        if let Some(preserve_aspect_ratio) = self.preserve_aspect_ratio {
-            let b = i32::from(preserve_aspect_ratio);
-            s.push_str(format!(";preserve_aspect_ratio={}", b).as_str());
-        }
+    /// set maximum height cells
+    ///
+    /// A height in cells larger than this is capped.
+    /// A height in pixels is capped too if it covers more cells with the size set by [`Builder::cell_pixels`].
+    /// Other heights, including none, are left as is, as the displayed size is not known.
+    pub fn max_height(mut self, v: u64) -> Builder<'a> {
+        self.max_height = Some(v);
+        self
+    }
 
-        if let Some(inline) = self.inline {
-            let b = i32::from(inline);
-            s.push_str(format!(";inline={}", b).as_str());
-        }
+    /// set cell size in pixels used to compare pixel sizes with `max_width` and `max_height`
+    pub fn cell_pixels(mut self, v: geometry::CellPixels) -> Builder<'a> {
+        self.cell_pixels = Some(v);
+        self
+    }
 
-        let encoded = to_base64_str(self.bytes);
-        s.push_str(format!(":{}\u{0007}", encoded).as_str());
+    #[cfg(feature = "terminal_size")]
+    fn fit_to(mut self, columns: u16, rows: u16) -> Builder<'a> {
+        self.width = Some(LengthUnit::Cell(u64::from(columns.max(1))));
+        self.height = Some(LengthUnit::Cell(u64::from(rows.max(1))));
+        self
+    }
 
-        s
+    /// set preserve_aspect_ratio
+    pub fn preserve_aspect_ratio(mut self, v: bool) -> Builder<'a> {
+        self.preserve_aspect_ratio = Some(v);
+        self
     }
-}
 
-fn to_base64_str(bytes: Vec<u8>) -> String {
-    base64::engine::general_purpose::STANDARD.encode(bytes)
-}
+    /// set inline
+    pub fn inline(mut self, v: bool) -> Builder<'a> {
+        self.inline = Some(v);
+        self
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// set doNotMoveCursor
+    ///
+    /// If true, the cursor is not moved after the image is displayed.
+    pub fn do_not_move_cursor(mut self, v: bool) -> Builder<'a> {
+        self.do_not_move_cursor = Some(v);
+        self
+    }
 
-    #[test]
-    fn name() {
-        let result = from_bytes(Vec::new()).name("xyz".to_string()).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;name=xyz:\u{0007}");
+    /// set download mode with filename
+    ///
+    /// Sets `inline=0` so that iTerm2 offers the payload as a file download.
+    /// iTerm2 ignores downloads without a name, so the filename is required.
+    pub fn download(self, name: impl Into<String>) -> Builder<'a> {
+        self.name(name).inline(false)
     }
 
-    #[test]
-    fn width() {
-        let result = from_bytes(Vec::new()).width(100).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;width=100:\u{0007}");
+    /// set raw mode
+    ///
+    /// If true, the sequence is followed by `\r\n` so that the cursor moves to the start of the next line
+    /// even when the terminal does not translate `\n` (e.g. raw mode TUIs).
+    pub fn raw_mode(mut self, v: bool) -> Builder<'a> {
+        self.raw_mode = Some(v);
+        self
     }
 
-    #[test]
-    fn width_px() {
-        let result = from_bytes(Vec::new()).width_px(100).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;width=100px:\u{0007}");
+    /// set OSC terminator, BEL by default
+    pub fn terminator(mut self, v: Terminator) -> Builder<'a> {
+        self.terminator = Some(v);
+        self
     }
 
-    #[test]
-    fn width_percent() {
-        let result = from_bytes(Vec::new()).width_percent(100).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;width=100%:\u{0007}");
+    /// set tmux passthrough
+    ///
+    /// If true, the sequence is wrapped in `ESC Ptmux; ... ESC \\` so that tmux passes it through to the terminal.
+    /// tmux must be configured with `set -g allow-passthrough on`.
+    pub fn tmux_passthrough(mut self, v: bool) -> Builder<'a> {
+        let v = if v {
+            Passthrough::Tmux
+        } else {
+            Passthrough::None
+        };
+        self.passthrough = Some(v);
+        self
     }
 
-    #[test]
-    fn width_auto() {
-        let result = from_bytes(Vec::new()).width_auto().build();
-        assert_eq!(result, "\x1b]1337;File=size=0;width=auto:\u{0007}");
+    /// set terminal multiplexer passthrough
+    pub fn passthrough(mut self, v: Passthrough) -> Builder<'a> {
+        self.passthrough = Some(v);
+        self
     }
 
-    #[test]
-    fn height() {
-        let result = from_bytes(Vec::new()).height(100).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;height=100:\u{0007}");
+    /// set maximum bytes per passthrough chunk
+    ///
+    /// The wrapped sequence is split into multiple passthrough sequences carrying at most `v` bytes each,
+    /// which the terminal receives as one sequence. The limit counts the escaped sequence only,
+    /// not the DCS prefix and `ESC \` terminator around each chunk. Has no effect without passthrough.
+    pub fn passthrough_chunk_len(mut self, v: usize) -> Builder<'a> {
+        self.passthrough_chunk_len = Some(v);
+        self
     }
 
-    #[test]
-    fn height_px() {
-        let result = from_bytes(Vec::new()).height_px(100).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;height=100px:\u{0007}");
+    /// set payload line width
+    ///
+    /// A line feed is inserted into the base64 payload every `v` characters. 0 disables wrapping.
+    pub fn line_width(mut self, v: usize) -> Builder<'a> {
+        self.line_width = Some(v);
+        self
     }
 
-    #[test]
-    fn height_percent() {
-        let result = from_bytes(Vec::new()).height_percent(100).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;height=100%:\u{0007}");
+    /// set whether `size` is emitted
+    ///
+    /// `size` is optional in the protocol, and can be omitted when the total length is not known up front,
+    /// as [`write_from_reader`](Builder::write_from_reader) does.
+    pub fn size_hint(mut self, v: bool) -> Builder<'a> {
+        self.size_hint = Some(v);
+        self
     }
 
-    #[test]
-    fn height_auto() {
-        let result = from_bytes(Vec::new()).height_auto().build();
-        assert_eq!(result, "\x1b]1337;File=size=0;height=auto:\u{0007}");
+    /// set base64 engine used for the payload and the filename
+    ///
+    /// Defaults to [`STANDARD`](base64::engine::general_purpose::STANDARD) as the protocol specifies.
+    /// A payload created by `from_base64` is not re-encoded, but decoded with this engine when needed.
+    pub fn base64_engine(mut self, v: base64::engine::GeneralPurpose) -> Builder<'a> {
+        self.engine = Some(v);
+        self
     }
 
-    #[test]
-    fn preserve_aspect_ratio() {
-        let result = from_bytes(Vec::new()).preserve_aspect_ratio(true).build();
-        assert_eq!(
-            result,
-            "\x1b]1337;File=size=0;preserve_aspect_ratio=1:\u{0007}"
-        );
+    /// set image id used by the Kitty backend
+    ///
+    /// Defaults to 1. Only the lower 24 bits are used.
+    pub fn image_id(mut self, v: u32) -> Builder<'a> {
+        self.image_id = Some(v);
+        self
+    }
 
-        let result = from_bytes(Vec::new()).preserve_aspect_ratio(false).build();
-        assert_eq!(
-            result,
-            "\x1b]1337;File=size=0;preserve_aspect_ratio=0:\u{0007}"
-        );
+    /// set Unicode placeholder placement used by the Kitty backend
+    ///
+    /// If true, the image is placed by placeholder characters printed after the sequence,
+    /// so that it moves with the text and survives scrolling and redraws.
+    /// Requires width and height in cells, up to 129 each.
+    pub fn unicode_placeholder(mut self, v: bool) -> Builder<'a> {
+        self.unicode_placeholder = Some(v);
+        self
     }
 
-    #[test]
-    fn inline() {
-        let result = from_bytes(Vec::new()).inline(true).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;inline=1:\u{0007}");
+    /// set brightness threshold used by the Braille backend
+    ///
+    /// Dots are lit for pixels at least this bright. Defaults to 128.
+    pub fn threshold(mut self, v: u8) -> Builder<'a> {
+        self.threshold = Some(v);
+        self
+    }
 
-        let result = from_bytes(Vec::new()).inline(false).build();
-        assert_eq!(result, "\x1b]1337;File=size=0;inline=0:\u{0007}");
+    /// set Floyd-Steinberg dithering used by the Braille backend
+    ///
+    /// If true, the threshold error is spread to the neighbouring dots, which shows gradients better.
+    pub fn dither(mut self, v: bool) -> Builder<'a> {
+        self.dither = Some(v);
+        self
     }
 
-    #[test]
-    fn all_options() {
-        let result = from_bytes(Vec::new())
-            .name("xyz".to_string())
+    /// strip metadata from the payload
+    ///
+    /// If true, EXIF (including GPS location), XMP, IPTC and text metadata are removed from JPEG and PNG payloads,
+    /// without re-encoding the image. Other formats are left unchanged.
+    /// This is applied to the current payload immediately, and drops the path if anything is removed.
+    /// Payloads that replace it later, such as re-encoded images and batch items, are stripped too.
+    ///
+    /// The EXIF orientation is removed too, so use [`Builder::auto_orient`] first if needed.
+    pub fn strip_metadata(mut self, v: bool) -> Builder<'a> {
+        self.strip_metadata = Some(v);
+        if !v {
+            return self;
+        }
+        let stripped = match &self.payload {
+            Payload::Base64 { encoded, .. } => match self.engine().decode(encoded) {
+                Ok(data) => metadata::strip(&data),
+                Err(_) => None,
+            },
+            payload => metadata::strip(payload.bytes().unwrap_or_default()),
+        };
+        if let Some(bytes) = stripped {
+            self.payload = Payload::Bytes(Cow::Owned(bytes));
+            #[cfg(feature = "std")]
+            {
+                self.path = None;
+            }
+        }
+        self
+    }
+
+    /// set JPEG quality used when the image is re-encoded
+    ///
+    /// The value is clamped to 1..=100. Used by `resize`, `convert_to`, `thumbnail` and the other transforms,
+    /// and as the highest quality tried by `target_payload_size`.
+    /// WebP is never produced, as only lossless WebP encoding is available.
+    pub fn quality(mut self, v: u8) -> Builder<'a> {
+        self.quality = Some(v.clamp(1, 100));
+        self
+    }
+
+    /// set strict format checking
+    ///
+    /// If true, `try_build` returns `Error::UnsupportedFormat` for payloads the backend cannot display.
+    /// Downloads with the iTerm2 backend are not checked.
+    pub fn strict(mut self, v: bool) -> Builder<'a> {
+        self.strict = Some(v);
+        self
+    }
+
+    /// set hyperlink
+    ///
+    /// The image is wrapped in an OSC 8 hyperlink, so that clicking it opens `url`.
+    pub fn hyperlink(mut self, url: impl Into<String>) -> Builder<'a> {
+        self.hyperlink = Some(url.into());
+        self
+    }
+
+    /// apply quirks of the terminal
+    ///
+    /// Only options that are not set yet are changed.
+    pub fn profile(self, v: TerminalProfile) -> Builder<'a> {
+        self.with_defaults(&v.defaults())
+    }
+
+    /// set graphics protocol
+    pub fn backend(mut self, v: Backend) -> Builder<'a> {
+        self.backend = Some(v);
+        self
+    }
+
+    /// set maximum payload bytes accepted by `try_build`
+    pub fn max_payload_len(mut self, v: usize) -> Builder<'a> {
+        self.max_payload_len = Some(v);
+        self
+    }
+
+    /// apply setters in place
+    ///
+    /// This allows configuring a builder conditionally without moving it,
+    /// and the builder can be reused since `write_to`, `build_into` and `to_string` do not consume it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut builder = iterm2img::from_bytes("abc".as_bytes());
+    /// let mut sequences = Vec::new();
+    /// for (i, width) in [5, 10].into_iter().enumerate() {
+    ///     builder.update(|b| b.width(width));
+    ///     if i > 0 {
+    ///         builder.update(|b| b.inline(true));
+    ///     }
+    ///     sequences.push(builder.to_string());
+    /// }
+    /// assert_eq!(sequences[0], "\x1b]1337;File=size=3;width=5:YWJj\u{0007}");
+    /// assert_eq!(sequences[1], "\x1b]1337;File=size=3;width=10;inline=1:YWJj\u{0007}");
+    /// ```
+    pub fn update(&mut self, f: impl FnOnce(Builder<'a>) -> Builder<'a>) -> &mut Builder<'a> {
+        let builder = core::mem::replace(self, from_slice(&[]));
+        *self = f(builder);
+        self
+    }
+
+    /// returns payload length in bytes
+    pub fn payload_len(&self) -> usize {
+        self.payload.len()
+    }
+
+    /// returns filename, or `None` if it is not valid UTF-8
+    pub fn get_name(&self) -> Option<&str> {
+        self.name
+            .as_deref()
+            .and_then(|name| core::str::from_utf8(name).ok())
+    }
+
+    /// returns filename bytes
+    pub fn get_name_bytes(&self) -> Option<&[u8]> {
+        self.name.as_deref()
+    }
+
+    /// returns whether filename is emitted without base64 encoding
+    pub fn get_raw_name(&self) -> Option<bool> {
+        self.raw_name
+    }
+
+    /// returns width
+    pub fn get_width(&self) -> Option<LengthUnit> {
+        self.width
+    }
+
+    /// returns height
+    pub fn get_height(&self) -> Option<LengthUnit> {
+        self.height
+    }
+
+    /// returns preserve_aspect_ratio
+    pub fn get_preserve_aspect_ratio(&self) -> Option<bool> {
+        self.preserve_aspect_ratio
+    }
+
+    /// returns inline
+    pub fn get_inline(&self) -> Option<bool> {
+        self.inline
+    }
+
+    /// returns doNotMoveCursor
+    pub fn get_do_not_move_cursor(&self) -> Option<bool> {
+        self.do_not_move_cursor
+    }
+
+    /// returns raw mode
+    pub fn get_raw_mode(&self) -> Option<bool> {
+        self.raw_mode
+    }
+
+    /// returns OSC terminator
+    pub fn get_terminator(&self) -> Option<Terminator> {
+        self.terminator
+    }
+
+    /// returns terminal multiplexer passthrough
+    pub fn get_passthrough(&self) -> Option<Passthrough> {
+        self.passthrough
+    }
+
+    /// returns maximum bytes per passthrough chunk
+    pub fn get_passthrough_chunk_len(&self) -> Option<usize> {
+        self.passthrough_chunk_len
+    }
+
+    /// returns maximum payload bytes accepted by `try_build`
+    pub fn get_max_payload_len(&self) -> Option<usize> {
+        self.max_payload_len
+    }
+
+    /// returns graphics protocol
+    pub fn get_backend(&self) -> Option<Backend> {
+        self.backend
+    }
+
+    /// returns hyperlink
+    pub fn get_hyperlink(&self) -> Option<&str> {
+        self.hyperlink.as_deref()
+    }
+
+    /// returns image id used by the Kitty backend
+    pub fn get_image_id(&self) -> Option<u32> {
+        self.image_id
+    }
+
+    /// returns Unicode placeholder placement used by the Kitty backend
+    pub fn get_unicode_placeholder(&self) -> Option<bool> {
+        self.unicode_placeholder
+    }
+
+    /// returns brightness threshold used by the Braille backend
+    pub fn get_threshold(&self) -> Option<u8> {
+        self.threshold
+    }
+
+    /// returns Floyd-Steinberg dithering used by the Braille backend
+    pub fn get_dither(&self) -> Option<bool> {
+        self.dither
+    }
+
+    /// returns whether metadata is stripped from the payload
+    pub fn get_strip_metadata(&self) -> Option<bool> {
+        self.strip_metadata
+    }
+
+    /// returns JPEG quality used when the image is re-encoded
+    pub fn get_quality(&self) -> Option<u8> {
+        self.quality
+    }
+
+    /// returns strict format checking
+    pub fn get_strict(&self) -> Option<bool> {
+        self.strict
+    }
+
+    /// returns the format of the payload from its magic bytes, or `None` if it is not known
+    pub fn detect_format(&self) -> Option<FileFormat> {
+        match &self.payload {
+            Payload::Base64 { encoded, .. } => {
+                let prefix = encoded.get(..format::MAGIC_LEN / 3 * 4).unwrap_or(encoded);
+                detect_format(&self.engine().decode(prefix).ok()?)
+            }
+            payload => detect_format(payload.bytes().unwrap_or_default()),
+        }
+    }
+
+    /// returns payload line width
+    pub fn get_line_width(&self) -> Option<usize> {
+        self.line_width
+    }
+
+    /// returns whether `size` is emitted
+    pub fn get_size_hint(&self) -> Option<bool> {
+        self.size_hint
+    }
+
+    /// returns maximum width cells
+    pub fn get_max_width(&self) -> Option<u64> {
+        self.max_width
+    }
+
+    /// returns maximum height cells
+    pub fn get_max_height(&self) -> Option<u64> {
+        self.max_height
+    }
+
+    /// returns cell size used to compare pixel sizes with the maximum cells
+    pub fn get_cell_pixels(&self) -> Option<geometry::CellPixels> {
+        self.cell_pixels
+    }
+
+    pub(crate) fn with_defaults(mut self, defaults: &Builder<'_>) -> Builder<'a> {
+        self.name = self.name.or_else(|| defaults.name.clone());
+        self.raw_name = self.raw_name.or(defaults.raw_name);
+        self.width = self.width.or(defaults.width);
+        self.height = self.height.or(defaults.height);
+        self.preserve_aspect_ratio = self
+            .preserve_aspect_ratio
+            .or(defaults.preserve_aspect_ratio);
+        self.inline = self.inline.or(defaults.inline);
+        self.do_not_move_cursor = self.do_not_move_cursor.or(defaults.do_not_move_cursor);
+        self.raw_mode = self.raw_mode.or(defaults.raw_mode);
+        self.terminator = self.terminator.or(defaults.terminator);
+        self.passthrough = self.passthrough.or(defaults.passthrough);
+        self.passthrough_chunk_len = self
+            .passthrough_chunk_len
+            .or(defaults.passthrough_chunk_len);
+        self.max_payload_len = self.max_payload_len.or(defaults.max_payload_len);
+        self.backend = self.backend.or(defaults.backend);
+        self.hyperlink = self.hyperlink.or_else(|| defaults.hyperlink.clone());
+        self.image_id = self.image_id.or(defaults.image_id);
+        self.unicode_placeholder = self.unicode_placeholder.or(defaults.unicode_placeholder);
+        self.threshold = self.threshold.or(defaults.threshold);
+        self.dither = self.dither.or(defaults.dither);
+        self.strict = self.strict.or(defaults.strict);
+        self.quality = self.quality.or(defaults.quality);
+        self.line_width = self.line_width.or(defaults.line_width);
+        self.engine = self.engine.or_else(|| defaults.engine.clone());
+        self.size_hint = self.size_hint.or(defaults.size_hint);
+        self.max_width = self.max_width.or(defaults.max_width);
+        self.max_height = self.max_height.or(defaults.max_height);
+        self.cell_pixels = self.cell_pixels.or(defaults.cell_pixels);
+        match (self.strip_metadata, defaults.strip_metadata) {
+            (None, Some(v)) => self.strip_metadata(v),
+            _ => self,
+        }
+    }
+
+    pub(crate) fn clamped_width(&self) -> Option<LengthUnit> {
+        let cell_px = self.cell_pixels.map(|cell| cell.width);
+        clamp_length(self.width, self.max_width, cell_px)
+    }
+
+    pub(crate) fn clamped_height(&self) -> Option<LengthUnit> {
+        let cell_px = self.cell_pixels.map(|cell| cell.height);
+        clamp_length(self.height, self.max_height, cell_px)
+    }
+
+    /// build string
+    pub fn build(self) -> String {
+        let bytes = self.build_bytes();
+        String::from_utf8(bytes).expect("sequence is valid UTF-8")
+    }
+
+    /// build string, returning an error for inputs that would produce a broken sequence
+    ///
+    /// Rejects a raw name containing `;`, `:` or control characters, zero percent width or height,
+    /// a payload longer than `max_payload_len`, `inline(false)` without a name which iTerm2 ignores,
+    /// inputs the backend cannot encode, for which `build` falls back to the iTerm2 sequence,
+    /// and, with `strict`, formats the backend cannot display.
+    ///
+    /// Use `download` to set a name together with `inline(false)`.
+    pub fn try_build(self) -> Result<String, Error> {
+        let backend = self.validate()?;
+        let mut buf = Vec::with_capacity(self.len_hint());
+        Pieces::with_backend(&self, backend).collect_into(&mut buf);
+        Ok(String::from_utf8(buf).expect("sequence is valid UTF-8"))
+    }
+
+    /// returns the backend sequence so that it is not encoded again
+    fn validate(&self) -> Result<Option<(String, String)>, Error> {
+        if let Some(name) = &self.name {
+            let invalid = |c: char| c == ';' || c == ':' || c.is_control();
+            let name = String::from_utf8_lossy(name);
+            if self.raw_name == Some(true) && name.contains(invalid) {
+                return Err(Error::InvalidName(name.into_owned()));
+            }
+        }
+        for v in [self.width, self.height].into_iter().flatten() {
+            if v == LengthUnit::Percent(0) {
+                return Err(Error::InvalidDimension(v));
+            }
+        }
+        if let Some(limit) = self.max_payload_len {
+            let len = self.payload.len();
+            if len > limit {
+                return Err(Error::PayloadTooLarge { len, limit });
+            }
+        }
+        let backend = self.backend.unwrap_or_default();
+        if backend == Backend::Iterm2 && self.inline == Some(false) && self.name.is_none() {
+            return Err(Error::MissingName);
+        }
+        let download = backend == Backend::Iterm2 && self.inline != Some(true);
+        if self.strict == Some(true) && !download {
+            match self.detect_format() {
+                Some(format) if backend.supports(format) => {}
+                format => return Err(Error::UnsupportedFormat(format)),
+            }
+        }
+        self.backend_sequence()
+    }
+
+    /// build sequence split into its parts
+    ///
+    /// Hyperlink, passthrough wrapping and raw mode line ending are not included.
+    pub fn sequence(&self) -> Sequence {
+        let encoded = self.encoded_payload();
+        let mut payload = Vec::with_capacity(encoded.len());
+        wrap_lines(encoded.as_bytes(), self.line_width, &mut 0, &mut payload);
+        Sequence::new(
+            self.osc_header(),
+            String::from_utf8(payload).expect("base64 is valid UTF-8"),
+            self.osc_terminator().to_string(),
+        )
+    }
+
+    /// build bytes
+    pub fn build_bytes(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.len_hint());
+        self.build_into_bytes(&mut buf);
+        buf
+    }
+
+    /// append sequence to string
+    pub fn build_into(&self, s: &mut String) {
+        use fmt::Write;
+        s.reserve(self.len_hint());
+        write!(s, "{}", self).expect("writing to String never fails");
+    }
+
+    /// append sequence to bytes
+    pub fn build_into_bytes(&self, buf: &mut Vec<u8>) {
+        buf.reserve(self.len_hint());
+        self.pieces().collect_into(buf);
+    }
+
+    /// build sequence into the buffer, replacing its contents
+    ///
+    /// The allocation of the buffer is reused, so that emitting an image every frame does not allocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut buf = Vec::new();
+    /// for frame in ["abc", "xyz"] {
+    ///     let builder = iterm2img::from_slice(frame.as_bytes()).inline(true);
+    ///     let sequence = builder.build_with_buffer(&mut buf);
+    ///     assert!(sequence.starts_with(b"\x1b]1337;File=size=3;inline=1:"));
+    /// }
+    /// ```
+    pub fn build_with_buffer<'b>(&self, buf: &'b mut Vec<u8>) -> &'b [u8] {
+        buf.clear();
+        self.build_into_bytes(buf);
+        buf
+    }
+
+    /// returns iterator over the sequence split into chunks of at most `size` bytes
+    ///
+    /// The payload is encoded lazily as chunks are requested.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    pub fn chunks(&self, size: usize) -> Chunks<'_> {
+        Chunks::new(self, size)
+    }
+
+    /// returns reader over the sequence
+    ///
+    /// The payload is encoded lazily as bytes are read.
+    #[cfg(feature = "std")]
+    pub fn reader(&self) -> SequenceReader<'_> {
+        SequenceReader::new(self)
+    }
+
+    /// write sequence to writer
+    ///
+    /// The payload is base64-encoded directly into the writer without building an intermediate string.
+    #[cfg(feature = "std")]
+    pub fn write_to(&self, w: &mut impl io::Write) -> io::Result<()> {
+        let mut pieces = self.pieces();
+        let mut piece = Vec::new();
+        while pieces.next_into(&mut piece) {
+            w.write_all(&piece)?;
+            piece.clear();
+        }
+        Ok(())
+    }
+
+    /// write sequence to writer with the payload read from `r`
+    ///
+    /// The reader is base64-encoded into the writer in chunks as it is read, so it is never held in memory.
+    /// The payload of the builder is ignored, and `size` is omitted since the length is not known up front.
+    /// Returns an `Unsupported` error for backends other than iTerm2, which need the whole image.
+    #[cfg(feature = "std")]
+    pub fn write_from_reader(&self, r: impl Read, w: &mut impl io::Write) -> io::Result<()> {
+        if self.backend.unwrap_or_default() != Backend::Iterm2 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only the iTerm2 backend can stream a reader",
+            ));
+        }
+        let builder = from_bytes(Vec::new()).with_defaults(self).size_hint(false);
+        stream::copy_encoded(&builder, r, w)
+    }
+
+    /// write sequence to stdout and flush
+    #[cfg(feature = "std")]
+    pub fn print(&self) -> io::Result<()> {
+        self.print_to(&mut io::stdout().lock())
+    }
+
+    /// write sequence to writer and flush
+    #[cfg(feature = "std")]
+    pub fn print_to(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.write_to(w)?;
+        w.flush()
+    }
+
+    /// write sequence to async writer
+    ///
+    /// The payload is base64-encoded in chunks without building an intermediate string.
+    #[cfg(feature = "tokio")]
+    pub async fn write_to_async(
+        &self,
+        w: &mut (impl tokio::io::AsyncWrite + Unpin),
+    ) -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut pieces = self.pieces();
+        let mut piece = Vec::new();
+        while pieces.next_into(&mut piece) {
+            w.write_all(&piece).await?;
+            piece.clear();
+        }
+        Ok(())
+    }
+
+    fn pieces(&self) -> Pieces<'_> {
+        Pieces::new(self)
+    }
+
+    fn framer(&self) -> Framer {
+        Framer::new(
+            self.passthrough.unwrap_or_default(),
+            self.passthrough_chunk_len,
+        )
+    }
+
+    /// returns length of the sequence to preallocate
+    ///
+    /// Exact for the iTerm2 backend unless the sequence is split into passthrough chunks.
+    fn len_hint(&self) -> usize {
+        if self.backend.unwrap_or_default() != Backend::Iterm2 {
+            return 0;
+        }
+        let encoded_len = match &self.payload {
+            Payload::Base64 { encoded, .. } => encoded.len(),
+            payload => {
+                use base64::engine::Config;
+                let padding = self.engine().config().encode_padding();
+                base64::encoded_len(payload.len(), padding).unwrap_or(0)
+            }
+        };
+        let breaks = match self.line_width {
+            Some(w) if w > 0 => encoded_len.saturating_sub(1) / w,
+            _ => 0,
+        };
+        // ESC only appears outside the payload
+        let control = [
+            self.hyperlink_start(),
+            self.osc_header(),
+            self.osc_terminator().to_string(),
+            self.hyperlink_end(),
+        ];
+        let control_len: usize = control.iter().map(String::len).sum();
+        let escapes = control.iter().map(|s| s.matches('\x1b').count()).sum();
+        let raw_len = control_len + encoded_len + breaks;
+        self.framer().framed_len(raw_len, escapes) + self.line_end().len()
+    }
+
+    fn osc_header(&self) -> String {
+        format!("\x1b]1337;File={}:", self.osc_args())
+    }
+
+    fn osc_args(&self) -> String {
+        let mut s = String::new();
+
+        if self.size_hint != Some(false) {
+            s.push_str(format!("size={}", self.payload.len()).as_str());
+        }
+
+        if let Some(name) = &self.name {
+            if self.raw_name == Some(true) {
+                s.push_str(format!(";name={}", escape_name(name)).as_str());
+            } else {
+                let name = self.engine().encode(name);
+                s.push_str(format!(";name={}", name).as_str());
+            }
+        }
+
+        if let Some(width) = self.clamped_width() {
+            s.push_str(format!(";width={}", width).as_str());
+        }
+
+        if let Some(height) = self.clamped_height() {
+            s.push_str(format!(";height={}", height).as_str());
+        }
+
+        if let Some(preserve_aspect_ratio) = self.preserve_aspect_ratio {
+            let b = i32::from(preserve_aspect_ratio);
+            s.push_str(format!(";preserve_aspect_ratio={}", b).as_str());
+        }
+
+        if let Some(inline) = self.inline {
+            let b = i32::from(inline);
+            s.push_str(format!(";inline={}", b).as_str());
+        }
+
+        if let Some(do_not_move_cursor) = self.do_not_move_cursor {
+            let b = i32::from(do_not_move_cursor);
+            s.push_str(format!(";doNotMoveCursor={}", b).as_str());
+        }
+
+        if s.starts_with(';') {
+            s.remove(0);
+        }
+        s
+    }
+
+    /// returns the whole sequence and the text printed after it for backends other than iTerm2
+    ///
+    /// Only the sequence is wrapped for passthrough.
+    fn backend_sequence(&self) -> Result<Option<(String, String)>, Error> {
+        let sequence = match self.backend.unwrap_or_default() {
+            Backend::Iterm2 => return Ok(None),
+            #[cfg(feature = "sixel")]
+            Backend::Sixel => self.sixel_sequence()?,
+            Backend::Terminology => self.terminology_sequence()?,
+            Backend::Kitty => return self.kitty_sequence().map(Some),
+            #[cfg(feature = "image")]
+            Backend::HalfBlocks => {
+                let text = self.text_art((1, 2), fallback::half_blocks)?;
+                return Ok(Some((String::new(), text)));
+            }
+            #[cfg(feature = "image")]
+            Backend::Braille => {
+                let threshold = self.threshold.unwrap_or(fallback::DEFAULT_THRESHOLD);
+                let dither = self.dither == Some(true);
+                let text = self.text_art((2, 4), |image, columns, rows, line_break| {
+                    fallback::braille(image, columns, rows, threshold, dither, line_break)
+                })?;
+                return Ok(Some((String::new(), text)));
+            }
+        };
+        Ok(Some((sequence, String::new())))
+    }
+
+    #[cfg(feature = "image")]
+    pub(crate) fn decoded_payload(&self) -> Result<Cow<'_, [u8]>, Error> {
+        match &self.payload {
+            Payload::Base64 { encoded, .. } => Ok(Cow::Owned(self.engine().decode(encoded)?)),
+            payload => Ok(Cow::Borrowed(payload.bytes().unwrap_or_default())),
+        }
+    }
+
+    #[cfg(feature = "sixel")]
+    fn sixel_sequence(&self) -> Result<String, Error> {
+        let px = |v: Option<LengthUnit>| match v {
+            Some(LengthUnit::Pixel(v)) => u32::try_from(v).ok(),
+            _ => None,
+        };
+        let data = self.decoded_payload()?;
+        sixel::encode(&data, px(self.clamped_width()), px(self.clamped_height()))
+    }
+
+    /// renders the image as text art with `cell` pixels per cell
+    #[cfg(feature = "image")]
+    fn text_art(
+        &self,
+        cell: (u32, u32),
+        render: impl FnOnce(&image::DynamicImage, u32, u32, &str) -> String,
+    ) -> Result<String, Error> {
+        let cells = |v: Option<LengthUnit>| match v {
+            Some(LengthUnit::Cell(v)) => Some(v),
+            _ => None,
+        };
+        let image = self.decode_image()?;
+        let (columns, rows) = fallback::grid(
+            &image,
+            cells(self.clamped_width()),
+            cells(self.clamped_height()),
+            cell,
+        );
+        let line_break = if self.raw_mode == Some(true) {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        Ok(render(&image, columns, rows, line_break))
+    }
+
+    fn kitty_sequence(&self) -> Result<(String, String), Error> {
+        let cells = match (self.clamped_width(), self.clamped_height()) {
+            (Some(LengthUnit::Cell(w)), Some(LengthUnit::Cell(h))) => Some((w, h)),
+            _ => None,
+        };
+        let unicode_placeholder = self.unicode_placeholder == Some(true);
+        let id = self.image_id.unwrap_or(1);
+        let text = if unicode_placeholder {
+            let (cols, rows) = cells.ok_or(Error::EncodeFailure(
+                "unicode placeholder requires width and height in cells",
+            ))?;
+            if cols > kitty::MAX_PLACEHOLDER_CELLS || rows > kitty::MAX_PLACEHOLDER_CELLS {
+                return Err(Error::EncodeFailure(
+                    "too many cells for unicode placeholder",
+                ));
+            }
+            kitty::placeholders(id, cols, rows)
+        } else {
+            String::new()
+        };
+        let transmission = kitty::Transmission {
+            id,
+            cells,
+            unicode_placeholder,
+        };
+        let sequence = kitty::encode(&self.encoded_payload(), &transmission);
+        Ok((sequence, text))
+    }
+
+    #[cfg(feature = "std")]
+    fn path_str(&self) -> Option<&str> {
+        self.path.as_ref().and_then(|p| p.to_str())
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn path_str(&self) -> Option<&str> {
+        None
+    }
+
+    fn terminology_sequence(&self) -> Result<String, Error> {
+        let path = self.path_str().ok_or(Error::EncodeFailure(
+            "terminology requires a UTF-8 file path",
+        ))?;
+        // the path is terminated by NUL, and ESC or BEL would end the sequence early
+        if path.contains(char::is_control) {
+            return Err(Error::EncodeFailure(
+                "terminology requires a file path without control characters",
+            ));
+        }
+        let (width, height) = match (self.clamped_width(), self.clamped_height()) {
+            (Some(LengthUnit::Cell(w)), Some(LengthUnit::Cell(h))) => (w, h),
+            _ => {
+                return Err(Error::EncodeFailure(
+                    "terminology requires width and height in cells",
+                ))
+            }
+        };
+        if width > MAX_TERMINOLOGY_CELLS || height > MAX_TERMINOLOGY_CELLS {
+            return Err(Error::EncodeFailure("too many cells for terminology"));
+        }
+        let mode = if self.preserve_aspect_ratio == Some(false) {
+            's'
+        } else {
+            'c'
+        };
+        let mut s = format!("\x1b}}i{}#{};{};{}\0", mode, width, height, path);
+        for _ in 0..height {
+            s.push_str("\x1b}ib\0");
+            (0..width).for_each(|_| s.push('#'));
+            s.push_str("\x1b}ie\0\n");
+        }
+        Ok(s)
+    }
+
+    fn engine(&self) -> &base64::engine::GeneralPurpose {
+        self.engine.as_ref().unwrap_or(&BASE64_ENGINE)
+    }
+
+    /// appends base64 of `bytes` to `out`
+    ///
+    /// With the `simd` feature, the default engine is replaced by a SIMD implementation.
+    fn encode_into(&self, bytes: &[u8], out: &mut Vec<u8>) {
+        let start = out.len();
+        #[cfg(feature = "simd")]
+        if self.engine.is_none() {
+            let simd = base64_simd::STANDARD;
+            out.resize(start + simd.encoded_length(bytes.len()), 0);
+            let n = simd
+                .encode(bytes, base64_simd::Out::from_slice(&mut out[start..]))
+                .len();
+            out.truncate(start + n);
+            return;
+        }
+        out.resize(start + bytes.len().div_ceil(3) * 4, 0);
+        let n = self
+            .engine()
+            .encode_slice(bytes, &mut out[start..])
+            .expect("buffer fits the encoded bytes");
+        out.truncate(start + n);
+    }
+
+    fn encoded_payload(&self) -> Cow<'_, str> {
+        match &self.payload {
+            Payload::Base64 { encoded, .. } => Cow::Borrowed(encoded),
+            payload => {
+                let mut out = Vec::new();
+                self.encode_into(payload.bytes().unwrap_or_default(), &mut out);
+                Cow::Owned(String::from_utf8(out).expect("base64 is ASCII"))
+            }
+        }
+    }
+
+    fn hyperlink_start(&self) -> String {
+        match &self.hyperlink {
+            Some(url) => {
+                let url: String = url.chars().filter(|c| !c.is_control()).collect();
+                format!("\x1b]8;;{}{}", url, self.osc_terminator())
+            }
+            None => String::new(),
+        }
+    }
+
+    fn hyperlink_end(&self) -> String {
+        match &self.hyperlink {
+            Some(_) => format!("\x1b]8;;{}", self.osc_terminator()),
+            None => String::new(),
+        }
+    }
+
+    fn osc_terminator(&self) -> &'static str {
+        match self.passthrough {
+            Some(Passthrough::Screen) => Terminator::Bel.as_str(),
+            _ => self.terminator.unwrap_or_default().as_str(),
+        }
+    }
+
+    fn line_end(&self) -> &'static str {
+        if self.raw_mode == Some(true) {
+            "\r\n"
+        } else {
+            ""
+        }
+    }
+}
+
+/// The sequence is encoded lazily into the formatter without building an intermediate string.
+impl fmt::Display for Builder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pieces = self.pieces();
+        let mut piece = Vec::new();
+        while pieces.next_into(&mut piece) {
+            f.write_str(core::str::from_utf8(&piece).map_err(|_| fmt::Error)?)?;
+            piece.clear();
+        }
+        Ok(())
+    }
+}
+
+// must be a multiple of 3 so that chunks are encoded without padding
+const ENCODE_CHUNK_LEN: usize = 3 * 1024;
+
+/// largest number of columns and rows of a Terminology placement
+const MAX_TERMINOLOGY_CELLS: u64 = 1024;
+
+#[cfg(feature = "std")]
+fn file_name(path: &Path) -> Option<Vec<u8>> {
+    let name = path.file_name()?;
+    Some(name.as_encoded_bytes().to_vec())
+}
+
+impl Payload<'_> {
+    fn into_owned(self) -> Payload<'static> {
+        match self {
+            Payload::Bytes(bytes) => Payload::Bytes(Cow::Owned(bytes.into_owned())),
+            #[cfg(feature = "bytes")]
+            Payload::Shared(bytes) => Payload::Shared(bytes),
+            #[cfg(feature = "mmap")]
+            Payload::Mapped(mmap) => Payload::Mapped(mmap),
+            Payload::Buffer(buffer) => Payload::Buffer(buffer),
+            Payload::Base64 {
+                encoded,
+                decoded_len,
+            } => Payload::Base64 {
+                encoded,
+                decoded_len,
+            },
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Payload::Bytes(bytes) => bytes.len(),
+            #[cfg(feature = "bytes")]
+            Payload::Shared(bytes) => bytes.len(),
+            #[cfg(feature = "mmap")]
+            Payload::Mapped(mmap) => mmap.len(),
+            Payload::Buffer(buffer) => buffer.as_ref().as_ref().len(),
+            Payload::Base64 { decoded_len, .. } => *decoded_len,
+        }
+    }
+
+    fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            Payload::Bytes(bytes) => Some(bytes),
+            #[cfg(feature = "bytes")]
+            Payload::Shared(bytes) => Some(bytes),
+            #[cfg(feature = "mmap")]
+            Payload::Mapped(mmap) => Some(mmap),
+            Payload::Buffer(buffer) => Some(buffer.as_ref().as_ref()),
+            Payload::Base64 { .. } => None,
+        }
+    }
+}
+
+fn standard_base64_decoded_len(s: &str) -> Option<usize> {
+    if !s.len().is_multiple_of(4) {
+        return None;
+    }
+    let data = s.trim_end_matches('=');
+    let padding = s.len() - data.len();
+    if padding > 2 {
+        return None;
+    }
+    if !data
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+    {
+        return None;
+    }
+    Some(s.len() / 4 * 3 - padding)
+}
+
+/// appends `chunk` inserting a line feed every `width` characters, `column` continues across chunks
+fn wrap_lines(chunk: &[u8], width: Option<usize>, column: &mut usize, out: &mut Vec<u8>) {
+    let width = match width {
+        Some(w) if w > 0 => w,
+        _ => {
+            out.extend_from_slice(chunk);
+            return;
+        }
+    };
+    let mut rest = chunk;
+    while !rest.is_empty() {
+        if *column == width {
+            out.push(b'\n');
+            *column = 0;
+        }
+        let n = (width - *column).min(rest.len());
+        out.extend_from_slice(&rest[..n]);
+        *column += n;
+        rest = &rest[n..];
+    }
+}
+
+/// caps cells, and pixels if the cell size is known, leaving other lengths unchanged
+fn clamp_length(
+    v: Option<LengthUnit>,
+    max: Option<u64>,
+    cell_px: Option<f64>,
+) -> Option<LengthUnit> {
+    match (v, max, cell_px) {
+        (Some(LengthUnit::Cell(v)), Some(max), _) => Some(LengthUnit::Cell(v.min(max))),
+        (Some(LengthUnit::Pixel(px)), Some(max), Some(cell_px))
+            if geometry::px_to_cells(px, cell_px, geometry::Rounding::Ceil) > max =>
+        {
+            Some(LengthUnit::Cell(max))
+        }
+        (v, _, _) => v,
+    }
+}
+
+fn escape_name(name: &[u8]) -> Cow<'_, str> {
+    let needs_escape = |c: char| matches!(c, ';' | ':' | '%') || c.is_control();
+    if let Ok(name) = core::str::from_utf8(name) {
+        if !name.contains(needs_escape) {
+            return Cow::Borrowed(name);
+        }
+    }
+    // bytes that are not valid UTF-8 are percent-escaped as well
+    let mut escaped = String::with_capacity(name.len());
+    for chunk in name.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if needs_escape(c) {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    escaped.push_str(&format!("%{:02X}", b));
+                }
+            } else {
+                escaped.push(c);
+            }
+        }
+        for b in chunk.invalid() {
+            escaped.push_str(&format!("%{:02X}", b));
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+fn percent_decode(s: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        if b != b'%' {
+            bytes.push(b);
+            continue;
+        }
+        let hex = [iter.next(), iter.next()];
+        let hex = match hex {
+            [Some(h), Some(l)] => [h, l],
+            _ => return Err(Error::InvalidDataUri),
+        };
+        let hex = core::str::from_utf8(&hex).map_err(|_| Error::InvalidDataUri)?;
+        let b = u8::from_str_radix(hex, 16).map_err(|_| Error::InvalidDataUri)?;
+        bytes.push(b);
+    }
+    Ok(bytes)
+}
+
+#[cfg(feature = "http")]
+fn url_file_name(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let path = path.split_once("://").map_or(path, |(_, rest)| rest);
+    let (_, name) = path.split_once('/')?;
+    let name = name.rsplit('/').next()?;
+    if name.is_empty() {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+#[cfg(feature = "std")]
+fn has_image_extension(path: &Path) -> bool {
+    const IMAGE_EXTENSIONS: [&str; 10] = [
+        "png", "jpg", "jpeg", "gif", "bmp", "tif", "tiff", "webp", "ico", "heic",
+    ];
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+const BASE64_ENGINE: base64::engine::GeneralPurpose = base64::engine::general_purpose::STANDARD;
+
+fn to_base64_str(bytes: &[u8]) -> String {
+    BASE64_ENGINE.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload_bytes<'a>(builder: &'a Builder) -> &'a [u8] {
+        builder.payload.bytes().expect("payload is not raw bytes")
+    }
+
+    #[test]
+    fn name() {
+        // $ echo -n xyz | base64
+        // eHl6
+        let result = from_bytes(Vec::new()).name("xyz".to_string()).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=eHl6:\u{0007}");
+
+        // $ echo -n 'a b;c.png' | base64
+        // YSBiO2MucG5n
+        let result = from_bytes(Vec::new()).name("a b;c.png".to_string()).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=YSBiO2MucG5n:\u{0007}");
+    }
+
+    #[test]
+    fn name_str() {
+        let result = from_bytes(Vec::new()).name("xyz").build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=eHl6:\u{0007}");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn late_payload() {
+        let builder = Builder::new().name("xyz").width(5);
+        assert_eq!(
+            builder.clone().build(),
+            "\x1b]1337;File=size=0;name=eHl6;width=5:\u{0007}"
+        );
+
+        let result = builder.clone().bytes("abc").build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;name=eHl6;width=5:YWJj\u{0007}"
+        );
+
+        let frame = vec![b'x'; 3];
+        let result = builder.payload(&frame).build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;name=eHl6;width=5:eHh4\u{0007}"
+        );
+
+        let result = super::from_path("examples/main.rs")
+            .unwrap()
+            .bytes("abc")
+            .build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;name=bWFpbi5ycw==:YWJj\u{0007}"
+        );
+    }
+
+    #[test]
+    fn update() {
+        let mut builder = from_bytes("abc".as_bytes());
+        builder.update(|b| b.width(5)).update(|b| b.name("xyz"));
+        assert_eq!(
+            builder.to_string(),
+            "\x1b]1337;File=size=3;name=eHl6;width=5:YWJj\u{0007}"
+        );
+
+        builder.update(|b| b.width_auto());
+        assert_eq!(
+            builder.build(),
+            "\x1b]1337;File=size=3;name=eHl6;width=auto:YWJj\u{0007}"
+        );
+    }
+
+    #[test]
+    fn getters() {
+        let builder = from_bytes("abc".as_bytes())
+            .name("xyz")
+            .width_px(10)
+            .inline(true)
+            .terminator(Terminator::St);
+        let cloned = builder.clone();
+        assert_eq!(cloned.payload_len(), 3);
+        assert_eq!(cloned.get_name(), Some("xyz"));
+        assert_eq!(cloned.get_width(), Some(LengthUnit::Pixel(10)));
+        assert_eq!(cloned.get_height(), None);
+        assert_eq!(cloned.get_inline(), Some(true));
+        assert_eq!(cloned.get_terminator(), Some(Terminator::St));
+        assert_eq!(cloned.build(), builder.build());
+
+        let debug = format!("{:?}", from_bytes("abc".as_bytes()));
+        assert!(debug.starts_with("Builder { payload: Bytes { len: 3 }"));
+    }
+
+    #[test]
+    fn raw_name() {
+        let result = from_bytes(Vec::new())
+            .name("xyz".to_string())
+            .raw_name(true)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=xyz:\u{0007}");
+
+        let result = from_bytes(Vec::new())
+            .name("xyz".to_string())
+            .raw_name(false)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=eHl6:\u{0007}");
+
+        let result = from_bytes(Vec::new())
+            .name("a;b:c%d\x07e\x1b]f\u{85}.png".to_string())
+            .raw_name(true)
+            .build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=0;name=a%3Bb%3Ac%25d%07e%1B]f%C2%85.png:\u{0007}"
+        );
+    }
+
+    #[test]
+    fn width() {
+        let result = from_bytes(Vec::new()).width(100).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;width=100:\u{0007}");
+    }
+
+    #[test]
+    fn width_px() {
+        let result = from_bytes(Vec::new()).width_px(100).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;width=100px:\u{0007}");
+    }
+
+    #[test]
+    fn width_percent() {
+        let result = from_bytes(Vec::new()).width_percent(100).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;width=100%:\u{0007}");
+    }
+
+    #[test]
+    fn width_auto() {
+        let result = from_bytes(Vec::new()).width_auto().build();
+        assert_eq!(result, "\x1b]1337;File=size=0;width=auto:\u{0007}");
+    }
+
+    #[test]
+    fn height() {
+        let result = from_bytes(Vec::new()).height(100).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;height=100:\u{0007}");
+    }
+
+    #[test]
+    fn height_px() {
+        let result = from_bytes(Vec::new()).height_px(100).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;height=100px:\u{0007}");
+    }
+
+    #[test]
+    fn height_percent() {
+        let result = from_bytes(Vec::new()).height_percent(100).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;height=100%:\u{0007}");
+    }
+
+    #[test]
+    fn height_auto() {
+        let result = from_bytes(Vec::new()).height_auto().build();
+        assert_eq!(result, "\x1b]1337;File=size=0;height=auto:\u{0007}");
+    }
+
+    #[test]
+    fn preserve_aspect_ratio() {
+        let result = from_bytes(Vec::new()).preserve_aspect_ratio(true).build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=0;preserve_aspect_ratio=1:\u{0007}"
+        );
+
+        let result = from_bytes(Vec::new()).preserve_aspect_ratio(false).build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=0;preserve_aspect_ratio=0:\u{0007}"
+        );
+    }
+
+    #[test]
+    fn inline() {
+        let result = from_bytes(Vec::new()).inline(true).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;inline=1:\u{0007}");
+
+        let result = from_bytes(Vec::new()).inline(false).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;inline=0:\u{0007}");
+    }
+
+    #[test]
+    fn raw_mode() {
+        let result = from_bytes(Vec::new()).raw_mode(true).build();
+        assert_eq!(result, "\x1b]1337;File=size=0:\u{0007}\r\n");
+
+        let result = from_bytes(Vec::new()).raw_mode(false).build();
+        assert_eq!(result, "\x1b]1337;File=size=0:\u{0007}");
+    }
+
+    #[test]
+    fn terminator() {
+        let result = from_bytes(Vec::new()).terminator(Terminator::St).build();
+        assert_eq!(result, "\x1b]1337;File=size=0:\x1b\\");
+
+        let result = from_bytes(Vec::new()).terminator(Terminator::Bel).build();
+        assert_eq!(result, "\x1b]1337;File=size=0:\u{0007}");
+    }
+
+    #[test]
+    fn tmux_passthrough() {
+        let result = from_bytes("abc".as_bytes()).tmux_passthrough(true).build();
+        assert_eq!(
+            result,
+            "\x1bPtmux;\x1b\x1b]1337;File=size=3:YWJj\u{0007}\x1b\\"
+        );
+
+        let result = from_bytes("abc".as_bytes())
+            .tmux_passthrough(true)
+            .terminator(Terminator::St)
+            .raw_mode(true)
+            .build();
+        assert_eq!(
+            result,
+            "\x1bPtmux;\x1b\x1b]1337;File=size=3:YWJj\x1b\x1b\\\x1b\\\r\n"
+        );
+
+        let result = from_bytes("abc".as_bytes()).tmux_passthrough(false).build();
+        assert_eq!(result, "\x1b]1337;File=size=3:YWJj\u{0007}");
+    }
+
+    #[test]
+    fn screen_passthrough() {
+        let result = from_bytes("abc".as_bytes())
+            .passthrough(Passthrough::Screen)
+            .terminator(Terminator::St)
+            .build();
+        assert_eq!(result, "\x1bP\x1b]1337;File=size=3:YWJj\u{0007}\x1b\\");
+
+        let bytes = vec![0; 3000];
+        let result = from_bytes(bytes.clone())
+            .passthrough(Passthrough::Screen)
+            .raw_mode(true)
+            .build();
+        let raw = from_bytes(bytes).build();
+        let expected: String = raw
+            .as_bytes()
+            .chunks(768)
+            .map(|c| format!("\x1bP{}\x1b\\", std::str::from_utf8(c).unwrap()))
+            .collect();
+        assert_eq!(result, expected + "\r\n");
+    }
+
+    #[test]
+    fn passthrough_chunk_len() {
+        let result = from_bytes("abc".as_bytes())
+            .tmux_passthrough(true)
+            .passthrough_chunk_len(16)
+            .build();
+        assert_eq!(
+            result,
+            "\x1bPtmux;\x1b\x1b]1337;File=siz\x1b\\\x1bPtmux;e=3:YWJj\u{0007}\x1b\\"
+        );
+
+        let result = from_bytes("abc".as_bytes())
+            .passthrough_chunk_len(16)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3:YWJj\u{0007}");
+    }
+
+    #[cfg(feature = "terminal_size")]
+    #[test]
+    fn fit_to() {
+        let actual = from_bytes("abc".as_bytes()).fit_to(80, 24).build();
+        let expected = "\x1b]1337;File=size=3;width=80;height=24:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let actual = from_bytes("abc".as_bytes()).fit_to(0, 0).build();
+        let expected = "\x1b]1337;File=size=3;width=1;height=1:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn max_width() {
+        let actual = from_bytes("abc".as_bytes())
+            .width(100)
+            .max_width(80)
+            .build();
+        let expected = "\x1b]1337;File=size=3;width=80:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let actual = from_bytes("abc".as_bytes()).width(10).max_width(80).build();
+        let expected = "\x1b]1337;File=size=3;width=10:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let actual = from_bytes("abc".as_bytes()).max_width(80).build();
+        let expected = "\x1b]1337;File=size=3:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let actual = from_bytes("abc".as_bytes())
+            .width_auto()
+            .max_width(80)
+            .build();
+        let expected = "\x1b]1337;File=size=3;width=auto:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let actual = from_bytes("abc".as_bytes())
+            .width_px(10)
+            .max_width(80)
+            .build();
+        let expected = "\x1b]1337;File=size=3;width=10px:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn max_height() {
+        let actual = from_bytes("abc".as_bytes())
+            .height(30)
+            .max_height(20)
+            .build();
+        let expected = "\x1b]1337;File=size=3;height=20:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let actual = from_bytes("abc".as_bytes())
+            .height_px(500)
+            .max_height(20)
+            .build();
+        let expected = "\x1b]1337;File=size=3;height=500px:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let cell = geometry::CellPixels {
+            width: 8.0,
+            height: 16.0,
+        };
+        let actual = from_bytes("abc".as_bytes())
+            .height_px(500)
+            .max_height(20)
+            .cell_pixels(cell)
+            .build();
+        let expected = "\x1b]1337;File=size=3;height=20:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+
+        let actual = from_bytes("abc".as_bytes())
+            .height_px(300)
+            .max_height(20)
+            .cell_pixels(cell)
+            .build();
+        let expected = "\x1b]1337;File=size=3;height=300px:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn length_unit_from_str() {
+        let cases = [
+            ("40", LengthUnit::Cell(40)),
+            ("300px", LengthUnit::Pixel(300)),
+            ("75%", LengthUnit::Percent(75)),
+            ("auto", LengthUnit::Auto),
+        ];
+        for (s, expected) in cases {
+            let actual: LengthUnit = s.parse().unwrap();
+            assert_eq!(actual, expected, "{}", s);
+            assert_eq!(actual.to_string(), s);
+        }
+
+        for s in ["", "px", "-1", "1.5", "10em", "Auto"] {
+            let result = s.parse::<LengthUnit>();
+            assert!(
+                matches!(result, Err(Error::InvalidLength(v)) if v == s),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn width_dim() {
+        let actual = from_bytes("abc".as_bytes())
+            .width_dim("75%".parse().unwrap())
+            .height_dim(LengthUnit::Cell(2))
+            .build();
+        let expected = "\x1b]1337;File=size=3;width=75%;height=2:YWJj\u{0007}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn size() {
+        let result = from_bytes(Vec::new())
+            .size(Dimensions::new(
+                LengthUnit::Pixel(10),
+                LengthUnit::Percent(50),
+            ))
+            .build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=0;width=10px;height=50%:\u{0007}"
+        );
+
+        let result = from_bytes(Vec::new())
+            .width(5)
+            .size(Dimensions::new(LengthUnit::Auto, LengthUnit::Cell(3)))
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=0;width=auto;height=3:\u{0007}");
+    }
+
+    #[test]
+    fn download() {
+        let result = from_bytes("abc".as_bytes())
+            .inline(true)
+            .download("xyz".to_string())
+            .build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;name=eHl6;inline=0:YWJj\u{0007}"
+        );
+    }
+
+    #[test]
+    fn do_not_move_cursor() {
+        let result = from_bytes(Vec::new()).do_not_move_cursor(true).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;doNotMoveCursor=1:\u{0007}");
+
+        let result = from_bytes(Vec::new()).do_not_move_cursor(false).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;doNotMoveCursor=0:\u{0007}");
+    }
+
+    #[test]
+    fn all_options() {
+        let result = from_bytes(Vec::new())
+            .name("xyz".to_string())
             .width(100)
             .height(200)
             .preserve_aspect_ratio(false)
             .inline(true)
+            .do_not_move_cursor(true)
+            .build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=0;name=eHl6;width=100;height=200;preserve_aspect_ratio=0;inline=1;doNotMoveCursor=1:\u{0007}"
+        );
+    }
+
+    #[test]
+    fn try_build() {
+        let result = from_bytes("abc".as_bytes())
+            .name("a;b".to_string())
+            .width_percent(50)
+            .max_payload_len(3)
+            .try_build()
+            .unwrap();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;name=YTti;width=50%:YWJj\u{0007}"
+        );
+
+        for name in ["a;b", "a:b", "a\x07b", "a\x1bb"] {
+            let result = from_bytes(Vec::new())
+                .name(name.to_string())
+                .raw_name(true)
+                .try_build();
+            assert!(matches!(result, Err(Error::InvalidName(n)) if n == name));
+        }
+
+        let result = from_bytes(Vec::new()).height_percent(0).try_build();
+        assert!(matches!(
+            result,
+            Err(Error::InvalidDimension(LengthUnit::Percent(0)))
+        ));
+        assert_eq!(result.unwrap_err().to_string(), "invalid dimension: 0%");
+
+        let result = from_bytes("abcd".as_bytes()).max_payload_len(3).try_build();
+        assert!(matches!(
+            result,
+            Err(Error::PayloadTooLarge { len: 4, limit: 3 })
+        ));
+
+        let result = from_bytes("abc".as_bytes()).try_build();
+        assert_eq!(result.unwrap(), "\x1b]1337;File=size=3:YWJj\u{0007}");
+
+        let result = from_bytes("abc".as_bytes()).inline(false).try_build();
+        assert!(matches!(result, Err(Error::MissingName)));
+
+        let result = from_bytes("abc".as_bytes()).inline(true).try_build();
+        assert_eq!(
+            result.unwrap(),
+            "\x1b]1337;File=size=3;inline=1:YWJj\u{0007}"
+        );
+
+        let result = from_bytes("abc".as_bytes()).download("xyz").try_build();
+        assert_eq!(
+            result.unwrap(),
+            "\x1b]1337;File=size=3;name=eHl6;inline=0:YWJj\u{0007}"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn backend_detect() {
+        assert_eq!(
+            Backend::detect_from("xterm-256color", "iTerm.app"),
+            Backend::Iterm2
+        );
+        assert_eq!(Backend::detect_from("xterm-256color", ""), Backend::Iterm2);
+        assert_eq!(
+            Backend::detect_from("terminology", ""),
+            Backend::Terminology
+        );
+        assert_eq!(Backend::detect_from("xterm-kitty", ""), Backend::Kitty);
+        #[cfg(feature = "sixel")]
+        assert_eq!(Backend::detect_from("foot", ""), Backend::Sixel);
+
+        assert!(matches!(
+            Backend::try_detect_from("xterm-256color", "WezTerm"),
+            Ok(Backend::Iterm2)
+        ));
+        assert!(matches!(
+            Backend::try_detect_from("xterm-kitty", ""),
+            Ok(Backend::Kitty)
+        ));
+        assert!(matches!(
+            Backend::try_detect_from("xterm-256color", ""),
+            Err(Error::UnsupportedTerminal)
+        ));
+    }
+
+    #[test]
+    fn kitty_backend() {
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Kitty)
+            .width(2)
+            .height(1)
+            .build();
+        assert_eq!(result, "\x1b_Ga=T,f=100,q=2,i=1,c=2,r=1,m=0;YWJj\x1b\\");
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Kitty)
+            .image_id(5)
+            .unicode_placeholder(true)
+            .width(2)
+            .height(1)
+            .tmux_passthrough(true)
+            .build();
+        assert_eq!(
+            result,
+            concat!(
+                "\x1bPtmux;\x1b\x1b_Ga=T,f=100,q=2,i=5,c=2,r=1,U=1,m=0;YWJj\x1b\x1b\\\x1b\\",
+                "\x1b[38;2;0;0;5m\u{10EEEE}\u{0305}\u{0305}\u{10EEEE}\u{0305}\u{030D}\x1b[39m",
+            )
+        );
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Kitty)
+            .unicode_placeholder(true)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3:YWJj\u{0007}");
+    }
+
+    #[test]
+    fn try_build_backend() {
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Terminology)
+            .width(1)
+            .height(1)
+            .try_build();
+        assert!(matches!(result, Err(Error::EncodeFailure(_))));
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Kitty)
+            .unicode_placeholder(true)
+            .try_build();
+        assert!(matches!(result, Err(Error::EncodeFailure(_))));
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Kitty)
+            .try_build()
+            .unwrap();
+        assert_eq!(result, "\x1b_Ga=T,f=100,q=2,i=1,m=0;YWJj\x1b\\");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn terminology_backend() {
+        let mut builder = from_bytes("abc".as_bytes())
+            .backend(Backend::Terminology)
+            .width(3)
+            .height(2);
+        builder.path = Some(PathBuf::from("/tmp/a.png"));
+        assert_eq!(
+            builder.build(),
+            concat!(
+                "\x1b}ic#3;2;/tmp/a.png\0",
+                "\x1b}ib\0###\x1b}ie\0\n",
+                "\x1b}ib\0###\x1b}ie\0\n",
+            )
+        );
+
+        let mut builder = from_bytes("abc".as_bytes())
+            .backend(Backend::Terminology)
+            .width(1)
+            .height(1)
+            .preserve_aspect_ratio(false);
+        builder.path = Some(PathBuf::from("/tmp/a.png"));
+        assert_eq!(
+            builder.build(),
+            "\x1b}is#1;1;/tmp/a.png\0\x1b}ib\0#\x1b}ie\0\n"
+        );
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Terminology)
+            .width(1)
+            .height(1)
+            .build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=3;width=1;height=1:YWJj\u{0007}"
+        );
+
+        let mut builder = from_bytes("abc".as_bytes())
+            .backend(Backend::Terminology)
+            .width(1)
+            .height(1);
+        builder.path = Some(PathBuf::from("/tmp/a\x07\x1b]0;x\0.png"));
+        assert!(matches!(builder.try_build(), Err(Error::EncodeFailure(_))));
+
+        let mut builder = from_bytes("abc".as_bytes())
+            .backend(Backend::Terminology)
+            .width(1_000_000)
+            .height(1_000_000);
+        builder.path = Some(PathBuf::from("/tmp/a.png"));
+        assert!(matches!(builder.try_build(), Err(Error::EncodeFailure(_))));
+    }
+
+    #[cfg(feature = "sixel")]
+    #[test]
+    fn sixel_backend() {
+        let png = png::encode_rgba(1, 1, &[255, 255, 255, 255]);
+        let result = from_bytes(png.clone())
+            .backend(Backend::Sixel)
+            .tmux_passthrough(true)
+            .build();
+        assert_eq!(
+            result,
+            "\x1bPtmux;\x1b\x1bP0;1;q\"1;1;1;1#215;2;100;100;100#215@$\x1b\x1b\\\x1b\\"
+        );
+
+        let result = super::from_base64(to_base64_str(&png), png.len())
+            .backend(Backend::Sixel)
+            .build();
+        assert_eq!(result, "\x1bP0;1;q\"1;1;1;1#215;2;100;100;100#215@$\x1b\\");
+
+        let result = from_bytes("abc".as_bytes()).backend(Backend::Sixel).build();
+        assert_eq!(result, "\x1b]1337;File=size=3:YWJj\u{0007}");
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Sixel)
+            .try_build();
+        assert!(matches!(result, Err(Error::EncodeFailure(_))));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn half_blocks_backend() {
+        let png = png::encode_rgba(1, 2, &[255, 0, 0, 255, 0, 0, 255, 255]);
+        let result = from_bytes(png.clone())
+            .backend(Backend::HalfBlocks)
+            .tmux_passthrough(true)
+            .build();
+        assert_eq!(result, "\x1b[38;2;255;0;0;48;2;0;0;255m▀\x1b[0m");
+
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let png = png::encode_rgba(1, 4, &[red, red, blue, blue].concat());
+        let result = from_bytes(png)
+            .backend(Backend::HalfBlocks)
+            .height(2)
+            .raw_mode(true)
+            .build();
+        let row = "\x1b[38;2;255;0;0;48;2;255;0;0m▀\x1b[0m";
+        let row2 = "\x1b[38;2;0;0;255;48;2;0;0;255m▀\x1b[0m";
+        assert_eq!(result, format!("{}\r\n{}\r\n", row, row2));
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::HalfBlocks)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3:YWJj\u{0007}");
+
+        let result = from_bytes(png::encode_rgba(1, 1, &[0; 4]))
+            .backend(Backend::HalfBlocks)
+            .width(u64::MAX / 2)
+            .height(1)
+            .build();
+        // columns are clamped to 1024
+        assert_eq!(result, format!("\x1b[49m{}\x1b[0m", " ".repeat(1024)));
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::HalfBlocks)
+            .try_build();
+        assert!(matches!(result, Err(Error::Image(_))));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn braille_backend() {
+        let white = [255, 255, 255, 255];
+        let gray = [100, 100, 100, 255];
+        let png = png::encode_rgba(2, 4, &[white, gray].repeat(4).concat());
+        let result = from_bytes(png.clone())
+            .backend(Backend::Braille)
+            .tmux_passthrough(true)
+            .build();
+        assert_eq!(result, "\u{2847}");
+
+        let result = from_bytes(png)
+            .backend(Backend::Braille)
+            .threshold(50)
+            .build();
+        assert_eq!(result, "\u{28ff}");
+
+        let result = from_bytes(png::encode_rgba(1, 1, &[0; 4]))
+            .backend(Backend::Braille)
+            .width(100_000)
+            .height(1)
+            .build();
+        // columns are clamped to 1024
+        assert_eq!(result, "\u{2800}".repeat(1024));
+
+        let result = from_bytes("abc".as_bytes())
+            .backend(Backend::Braille)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3:YWJj\u{0007}");
+    }
+
+    #[test]
+    fn detect_format() {
+        let png = png::encode_rgba(1, 1, &[0, 0, 0, 0]);
+        assert_eq!(from_slice(&png).detect_format(), Some(FileFormat::Png));
+        let builder = super::from_base64(to_base64_str(&png), png.len());
+        assert_eq!(builder.detect_format(), Some(FileFormat::Png));
+        let builder = super::from_base64(to_base64_str(b"GIF89a"), 6);
+        assert_eq!(builder.detect_format(), Some(FileFormat::Gif));
+        assert_eq!(from_bytes("abc".as_bytes()).detect_format(), None);
+    }
+
+    #[test]
+    fn strip_metadata() {
+        let exif = [0xff, 0xe1, 0x00, 0x08, b'E', b'x', b'i', b'f', 0, 0];
+        let data = [&[0xff, 0xd8][..], &exif, &[0xff, 0xd9]].concat();
+        let result = from_slice(&data).strip_metadata(true).build();
+        // $ printf '\xff\xd8\xff\xd9' | base64
+        // /9j/2Q==
+        assert_eq!(result, "\x1b]1337;File=size=4:/9j/2Q==\u{0007}");
+
+        let result = super::from_base64(to_base64_str(&data), data.len())
+            .strip_metadata(true)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=4:/9j/2Q==\u{0007}");
+
+        let result = from_slice(&data).strip_metadata(false).build();
+        assert_eq!(result, from_slice(&data).build());
+
+        let defaults = from_bytes(Vec::new()).strip_metadata(true);
+        let builder = from_slice(&data).with_defaults(&defaults);
+        assert_eq!(builder.get_strip_metadata(), Some(true));
+        assert_eq!(builder.build(), "\x1b]1337;File=size=4:/9j/2Q==\u{0007}");
+
+        let builder = from_slice(&data)
+            .strip_metadata(false)
+            .with_defaults(&defaults);
+        assert_eq!(builder.get_strip_metadata(), Some(false));
+        assert_eq!(builder.build(), from_slice(&data).build());
+    }
+
+    #[test]
+    fn strict() {
+        let png = png::encode_rgba(1, 1, &[0, 0, 0, 0]);
+        let result = from_slice(&png).inline(true).strict(true).try_build();
+        assert!(result.is_ok());
+
+        let result = from_bytes("abc".as_bytes())
+            .inline(true)
+            .strict(true)
+            .try_build();
+        assert!(matches!(result, Err(Error::UnsupportedFormat(None))));
+
+        let result = from_bytes("abc".as_bytes())
+            .name("abc.txt")
+            .strict(true)
+            .try_build();
+        assert!(result.is_ok());
+
+        let result = from_bytes("%PDF-1.7".as_bytes())
+            .backend(Backend::Kitty)
+            .strict(true)
+            .try_build();
+        assert!(matches!(
+            result,
+            Err(Error::UnsupportedFormat(Some(FileFormat::Pdf)))
+        ));
+
+        let result = from_bytes("abc".as_bytes()).inline(true).try_build();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn line_width() {
+        // $ echo -n abcdefghijkl | base64
+        // YWJjZGVmZ2hpamts
+        let result = from_bytes("abcdefghijkl".as_bytes()).line_width(6).build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=12:YWJjZG\nVmZ2hp\namts\u{0007}"
+        );
+
+        let result = from_bytes("abcdefghijkl".as_bytes()).line_width(8).build();
+        assert_eq!(result, "\x1b]1337;File=size=12:YWJjZGVm\nZ2hpamts\u{0007}");
+
+        let result = from_bytes("abcdefghijkl".as_bytes()).line_width(0).build();
+        assert_eq!(result, "\x1b]1337;File=size=12:YWJjZGVmZ2hpamts\u{0007}");
+
+        let bytes: Vec<u8> = (0..10000).map(|i| i as u8).collect();
+        let result = from_bytes(bytes.clone()).line_width(76).build();
+        let encoded = to_base64_str(&bytes);
+        let lines: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(76)
+            .map(|c| std::str::from_utf8(c).unwrap())
+            .collect();
+        assert_eq!(
+            result,
+            format!("\x1b]1337;File=size=10000:{}\u{0007}", lines.join("\n"))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn profile() {
+        let result = from_bytes("abc".as_bytes())
+            .profile(TerminalProfile::WezTerm)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3;inline=1:YWJj\u{0007}");
+
+        let result = from_bytes("abc".as_bytes())
+            .inline(false)
+            .profile(TerminalProfile::Konsole)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3;inline=0:YWJj\u{0007}");
+
+        let result = from_bytes(vec![0; 20_000_001])
+            .profile(TerminalProfile::VSCode)
+            .try_build();
+        assert!(matches!(result, Err(Error::PayloadTooLarge { .. })));
+
+        assert_eq!(
+            TerminalProfile::detect_from("vscode", false),
+            Some(TerminalProfile::VSCode)
+        );
+        assert_eq!(
+            TerminalProfile::detect_from("", true),
+            Some(TerminalProfile::Konsole)
+        );
+        assert_eq!(TerminalProfile::detect_from("", false), None);
+    }
+
+    #[test]
+    fn size_hint() {
+        let result = from_bytes("abc".as_bytes()).size_hint(false).build();
+        assert_eq!(result, "\x1b]1337;File=:YWJj\u{0007}");
+
+        let result = from_bytes("abc".as_bytes())
+            .size_hint(false)
+            .name("xyz".to_string())
+            .inline(true)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=name=eHl6;inline=1:YWJj\u{0007}");
+
+        let result = from_bytes("abc".as_bytes()).size_hint(true).build();
+        assert_eq!(result, "\x1b]1337;File=size=3:YWJj\u{0007}");
+    }
+
+    #[test]
+    fn base64_engine() {
+        // $ echo -n '??>' | base64
+        // Pz8+
+        let result = from_bytes("??>".as_bytes())
+            .name("a?".to_string())
+            .base64_engine(base64::engine::general_purpose::URL_SAFE)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3;name=YT8=:Pz8-\u{0007}");
+
+        let result = from_bytes("ab".as_bytes())
+            .base64_engine(base64::engine::general_purpose::STANDARD_NO_PAD)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=2:YWI\u{0007}");
+    }
+
+    #[test]
+    fn hyperlink() {
+        let result = from_bytes("abc".as_bytes())
+            .hyperlink("https://example.com/a.png".to_string())
+            .build();
+        assert_eq!(
+            result,
+            "\x1b]8;;https://example.com/a.png\u{0007}\x1b]1337;File=size=3:YWJj\u{0007}\x1b]8;;\u{0007}"
+        );
+
+        let result = from_bytes("abc".as_bytes())
+            .hyperlink("file:///tmp/a\x07.png".to_string())
+            .terminator(Terminator::St)
+            .tmux_passthrough(true)
             .build();
         assert_eq!(
             result,
-            "\x1b]1337;File=size=0;name=xyz;width=100;height=200;preserve_aspect_ratio=0;inline=1:\u{0007}"
+            "\x1bPtmux;\x1b\x1b]8;;file:///tmp/a.png\x1b\x1b\\\x1b\x1b]1337;File=size=3:YWJj\x1b\x1b\\\x1b\x1b]8;;\x1b\x1b\\\x1b\\"
         );
     }
 
@@ -268,4 +3001,489 @@ mod tests {
         let result = from_bytes("abcdefg".as_bytes().to_vec()).build();
         assert_eq!(result, "\x1b]1337;File=size=7:YWJjZGVmZw==\u{0007}")
     }
+
+    #[test]
+    fn build_bytes() {
+        let result = from_bytes("abcdefg".as_bytes()).width(5).build_bytes();
+        assert_eq!(result, b"\x1b]1337;File=size=7;width=5:YWJjZGVmZw==\x07");
+    }
+
+    #[test]
+    fn encode_into() {
+        let bytes: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        for len in [0, 1, 2, 3, 4, 100, 3072, 10_000] {
+            let mut out = b"x".to_vec();
+            from_slice(&[]).encode_into(&bytes[..len], &mut out);
+            let expected = format!("x{}", to_base64_str(&bytes[..len]));
+            assert_eq!(out, expected.as_bytes(), "{}", len);
+        }
+    }
+
+    #[test]
+    fn build_with_buffer() {
+        let mut buf = Vec::new();
+        let result = from_bytes("abcdefg".as_bytes()).build_with_buffer(&mut buf);
+        assert_eq!(result, b"\x1b]1337;File=size=7:YWJjZGVmZw==\x07");
+
+        let capacity = buf.capacity();
+        let result = from_bytes("abc".as_bytes()).build_with_buffer(&mut buf);
+        assert_eq!(result, b"\x1b]1337;File=size=3:YWJj\x07");
+        assert_eq!(buf.capacity(), capacity);
+    }
+
+    #[test]
+    fn len_hint() {
+        let no_pad = base64::engine::GeneralPurpose::new(
+            &base64::alphabet::STANDARD,
+            base64::engine::general_purpose::NO_PAD,
+        );
+        let builders = [
+            from_bytes("abcdefg".as_bytes()),
+            from_bytes(Vec::new()),
+            from_bytes("abcdefg".as_bytes()).name("xyz").width(5),
+            from_bytes("abcdefg".as_bytes()).base64_engine(no_pad),
+            from_bytes("abcdefg".as_bytes())
+                .line_width(4)
+                .raw_mode(true),
+            from_bytes("abcdefg".as_bytes()).line_width(12),
+            from_bytes("abcdefg".as_bytes())
+                .hyperlink("https://example.com")
+                .terminator(Terminator::St),
+            from_bytes("abcdefg".as_bytes()).tmux_passthrough(true),
+            from_bytes("abcdefg".as_bytes()).passthrough(Passthrough::Screen),
+            super::from_base64("YWJjZGVmZw==".to_string(), 7),
+        ];
+        for builder in builders {
+            let hint = builder.len_hint();
+            assert_eq!(hint, builder.build_bytes().len());
+        }
+    }
+
+    #[test]
+    fn build_into() {
+        let first = from_bytes("abc".as_bytes());
+        let second = from_bytes("xyz".as_bytes()).width(5);
+        let expected =
+            "\x1b]1337;File=size=3:YWJj\u{0007}\x1b]1337;File=size=3;width=5:eHl6\u{0007}";
+
+        let mut s = String::new();
+        first.build_into(&mut s);
+        second.build_into(&mut s);
+        assert_eq!(s, expected);
+
+        let mut buf = Vec::new();
+        first.build_into_bytes(&mut buf);
+        second.build_into_bytes(&mut buf);
+        assert_eq!(buf, expected.as_bytes());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_to() {
+        let bytes: Vec<u8> = (0..10000).map(|i| (i % 256) as u8).collect();
+        let builders = [
+            from_bytes(bytes.clone()).name("xyz".to_string()).width(10),
+            super::from_base64(to_base64_str(&bytes), bytes.len()).inline(true),
+        ];
+        for builder in builders {
+            let mut buf = Vec::new();
+            builder.write_to(&mut buf).unwrap();
+            assert_eq!(String::from_utf8(buf).unwrap(), builder.build());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn print_to() {
+        struct FlushCheck {
+            buf: Vec<u8>,
+            flushed: bool,
+        }
+
+        impl io::Write for FlushCheck {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.flushed = false;
+                self.buf.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                self.flushed = true;
+                Ok(())
+            }
+        }
+
+        let mut w = FlushCheck {
+            buf: Vec::new(),
+            flushed: false,
+        };
+        let builder = from_bytes("abcdefg".as_bytes());
+        builder.print_to(&mut w).unwrap();
+        assert_eq!(w.buf, builder.build_bytes());
+        assert!(w.flushed);
+    }
+
+    #[test]
+    fn display() {
+        let bytes: Vec<u8> = (0..10000).map(|i| (i % 256) as u8).collect();
+        let builders = [
+            from_bytes(bytes.clone()).name("xyz".to_string()).width(10),
+            super::from_base64(to_base64_str(&bytes), bytes.len()).inline(true),
+        ];
+        for builder in builders {
+            assert_eq!(format!("{}", builder), builder.build());
+        }
+    }
+
+    #[test]
+    fn from_slice_borrowed() {
+        static BYTES: &[u8] = b"abcdefg";
+        let builder = super::from_slice(BYTES);
+        assert!(matches!(builder.payload, Payload::Bytes(Cow::Borrowed(_))));
+        assert_eq!(
+            builder.build(),
+            "\x1b]1337;File=size=7:YWJjZGVmZw==\u{0007}"
+        );
+    }
+
+    #[test]
+    fn name_bytes() {
+        let result = from_bytes(Vec::new())
+            .name_bytes(b"a\xffb".to_vec())
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=Yf9i:\u{0007}");
+
+        let result = from_bytes(Vec::new())
+            .name_bytes(b"a\xff;b".to_vec())
+            .raw_name(true)
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=a%FF%3Bb:\u{0007}");
+
+        let builder = from_bytes(Vec::new()).name_bytes(b"a\xffb".to_vec());
+        assert_eq!(builder.get_name(), None);
+        assert_eq!(builder.get_name_bytes(), Some(&b"a\xffb"[..]));
+    }
+
+    #[cfg(feature = "std")]
+    #[cfg(unix)]
+    #[test]
+    fn name_os() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let name = OsStr::from_bytes(b"a\xffb");
+        let result = from_bytes(Vec::new()).name_os(name).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=Yf9i:\u{0007}");
+
+        let result = from_bytes(Vec::new()).name_os(Path::new("xyz")).build();
+        assert_eq!(result, "\x1b]1337;File=size=0;name=eHl6:\u{0007}");
+    }
+
+    #[test]
+    fn from_cow() {
+        let expected = "\x1b]1337;File=size=3:YWJj\u{0007}";
+
+        let builder = super::from_cow("abc".as_bytes());
+        assert!(matches!(builder.payload, Payload::Bytes(Cow::Borrowed(_))));
+        assert_eq!(builder.build(), expected);
+
+        let builder = super::from_cow("abc".as_bytes().to_vec());
+        assert!(matches!(builder.payload, Payload::Bytes(Cow::Owned(_))));
+        assert_eq!(builder.build(), expected);
+    }
+
+    #[test]
+    fn from_buffer() {
+        let expected = "\x1b]1337;File=size=3:YWJj\u{0007}";
+
+        let buffer: Arc<[u8]> = Arc::from("abc".as_bytes());
+        let builder = super::from_buffer(buffer);
+        assert!(matches!(builder.payload, Payload::Buffer(_)));
+        assert_eq!(builder.clone().build(), expected);
+        assert_eq!(builder.build(), expected);
+
+        assert_eq!(super::from_buffer(String::from("abc")).build(), expected);
+        assert_eq!(super::from_buffer(*b"abc").build(), expected);
+    }
+
+    #[test]
+    fn into_owned() {
+        let builder = {
+            let bytes = "abc".as_bytes().to_vec();
+            super::from_slice(&bytes)
+                .name("xyz")
+                .inline(true)
+                .into_owned()
+        };
+        assert!(matches!(builder.payload, Payload::Bytes(Cow::Owned(_))));
+        assert_eq!(
+            builder.build(),
+            "\x1b]1337;File=size=3;name=eHl6;inline=1:YWJj\u{0007}"
+        );
+    }
+
+    #[test]
+    fn from_bytes_types() {
+        let expected = "\x1b]1337;File=size=3:YWJj\u{0007}";
+        assert_eq!(super::from_bytes(vec![b'a', b'b', b'c']).build(), expected);
+        assert_eq!(super::from_bytes(*b"abc").build(), expected);
+        assert_eq!(super::from_bytes(&b"abc"[..]).build(), expected);
+        assert_eq!(super::from_slice(b"abc").build(), expected);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn from_shared() {
+        let bytes = bytes::Bytes::from_static(b"abcdefg");
+        let result = super::from_shared(bytes).build();
+        assert_eq!(result, "\x1b]1337;File=size=7:YWJjZGVmZw==\u{0007}");
+    }
+
+    #[test]
+    fn from_base64() {
+        let result = super::from_base64("YWJjZGVmZw==".to_string(), 7).build();
+        assert_eq!(result, "\x1b]1337;File=size=7:YWJjZGVmZw==\u{0007}");
+    }
+
+    #[test]
+    fn from_data_uri() {
+        let expected = "\x1b]1337;File=size=7:YWJjZGVmZw==\u{0007}";
+        let cases = [
+            "data:image/png;base64,YWJjZGVmZw==",
+            "data:;base64,YWJjZGVmZw",
+            "data:text/plain,abc%64efg",
+        ];
+        for uri in cases {
+            let result = super::from_data_uri(uri).unwrap().build();
+            assert_eq!(result, expected, "{}", uri);
+        }
+
+        let builder = super::from_data_uri("data:image/png;base64,YWJjZGVmZw==").unwrap();
+        assert!(matches!(builder.payload, Payload::Base64 { .. }));
+
+        // $ echo -n '???' | base64
+        // Pz8/
+        let result = super::from_data_uri("data:image/png;base64,Pz8_")
+            .unwrap()
+            .build();
+        assert_eq!(result, "\x1b]1337;File=size=3:Pz8/\u{0007}");
+
+        let cases = [
+            "image/png;base64,YWJj",
+            "data:image/png;base64",
+            "data:image/png;base64,YW!j",
+            "data:text/plain,abc%6",
+        ];
+        for uri in cases {
+            let result = super::from_data_uri(uri);
+            assert!(matches!(result, Err(Error::InvalidDataUri)), "{}", uri);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_file() {
+        let path = std::env::temp_dir().join("iterm2img_from_file.txt");
+        fs::write(&path, "abcdefg").unwrap();
+
+        // $ echo -n iterm2img_from_file.txt | base64
+        // aXRlcm0yaW1nX2Zyb21fZmlsZS50eHQ=
+        let result = super::from_file(&path).unwrap().build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=7;name=aXRlcm0yaW1nX2Zyb21fZmlsZS50eHQ=:YWJjZGVmZw==\u{0007}"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_path() {
+        let path = std::env::temp_dir().join("iterm2img_from_path.PNG");
+        fs::write(&path, "abcdefg").unwrap();
+
+        // $ echo -n iterm2img_from_path.PNG | base64
+        // aXRlcm0yaW1nX2Zyb21fcGF0aC5QTkc=
+        let result = super::from_path(&path).unwrap().build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=7;name=aXRlcm0yaW1nX2Zyb21fcGF0aC5QTkc=;inline=1:YWJjZGVmZw==\u{0007}"
+        );
+
+        fs::remove_file(&path).unwrap();
+
+        let path = std::env::temp_dir().join("iterm2img_from_path.txt");
+        fs::write(&path, "abcdefg").unwrap();
+
+        let result = super::from_path(&path).unwrap().build();
+        assert!(!result.contains("inline"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_path_not_found() {
+        let path = std::env::temp_dir().join("iterm2img_not_found.png");
+        let result = super::from_path(&path);
+        assert!(matches!(
+            result,
+            Err(Error::ReadFile { path: p, source }) if p == path && source.kind() == io::ErrorKind::NotFound
+        ));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn from_file_mmap() {
+        let path = std::env::temp_dir().join("iterm2img_from_file.mmap");
+        fs::write(&path, "abcdefg").unwrap();
+
+        // $ echo -n iterm2img_from_file.mmap | base64
+        // aXRlcm0yaW1nX2Zyb21fZmlsZS5tbWFw
+        let result = super::from_file_mmap(&path).unwrap().build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=7;name=aXRlcm0yaW1nX2Zyb21fZmlsZS5tbWFw:YWJjZGVmZw==\u{0007}"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_file_not_found() {
+        let path = std::env::temp_dir().join("iterm2img_not_found.txt");
+        assert!(super::from_file(path).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn from_reader() {
+        let reader = io::Cursor::new("abcdefg");
+        let result = super::from_reader(reader).unwrap().build();
+        assert_eq!(result, "\x1b]1337;File=size=7:YWJjZGVmZw==\u{0007}");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn write_from_reader() {
+        let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        let builders = [
+            from_bytes(Vec::new()).inline(true),
+            from_bytes(Vec::new()).line_width(76).tmux_passthrough(true),
+        ];
+        for builder in builders {
+            let (a, b) = data.split_at(1000);
+            let reader = io::Cursor::new(a).chain(io::Cursor::new(b));
+            let mut out = Vec::new();
+            builder.write_from_reader(reader, &mut out).unwrap();
+            let expected = from_bytes(data.clone())
+                .with_defaults(&builder)
+                .size_hint(false)
+                .build_bytes();
+            assert_eq!(out, expected);
+        }
+
+        let mut out = Vec::new();
+        from_bytes(Vec::new())
+            .write_from_reader(io::empty(), &mut out)
+            .unwrap();
+        assert_eq!(out, b"\x1b]1337;File=:\x07");
+
+        let result = from_bytes(Vec::new())
+            .backend(Backend::Kitty)
+            .write_from_reader(io::empty(), &mut Vec::new());
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn url_file_name() {
+        let cases = [
+            ("https://example.com/a/image.png", Some("image.png")),
+            ("https://example.com/image.png?w=100#top", Some("image.png")),
+            ("https://example.com/a/", None),
+            ("https://example.com", None),
+        ];
+        for (url, expected) in cases {
+            let result = super::url_file_name(url);
+            assert_eq!(result.as_deref(), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn from_rgba() {
+        let builder = super::from_rgba(2, 1, &[0; 8]).unwrap();
+        assert!(payload_bytes(&builder).starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        let result = super::from_rgba(2, 2, &[0; 8]);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidPixelLength {
+                expected: 16,
+                actual: 8
+            })
+        ));
+
+        for (width, height) in [(0, 0), (0, 1), (1, 0)] {
+            let result = super::from_rgba(width, height, &[]);
+            assert!(matches!(
+                result,
+                Err(Error::InvalidImageSize { width: w, height: h }) if (w, h) == (width, height)
+            ));
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn from_image() {
+        let image = image::DynamicImage::new_rgba8(2, 3);
+        let builder = super::from_image(&image).unwrap();
+
+        let decoded = image::load_from_memory(payload_bytes(&builder)).unwrap();
+        assert_eq!(decoded.width(), 2);
+        assert_eq!(decoded.height(), 3);
+
+        let result = builder.build();
+        assert!(result.starts_with("\x1b]1337;File=size="));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn write_to_async() {
+        let bytes: Vec<u8> = (0..100000).map(|i| (i % 256) as u8).collect();
+        let builders = [
+            from_bytes(bytes.clone()).name("xyz".to_string()).width(10),
+            super::from_base64(to_base64_str(&bytes), bytes.len()).inline(true),
+        ];
+        for builder in builders {
+            let mut buf = Vec::new();
+            builder.write_to_async(&mut buf).await.unwrap();
+            assert_eq!(String::from_utf8(buf).unwrap(), builder.build());
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn from_async_reader() {
+        let reader = io::Cursor::new("abcdefg");
+        let result = super::from_async_reader(reader).await.unwrap().build();
+        assert_eq!(result, "\x1b]1337;File=size=7:YWJjZGVmZw==\u{0007}");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn from_async_file() {
+        let path = std::env::temp_dir().join("iterm2img_from_async_file.txt");
+        fs::write(&path, "abcdefg").unwrap();
+
+        // $ echo -n iterm2img_from_async_file.txt | base64
+        // aXRlcm0yaW1nX2Zyb21fYXN5bmNfZmlsZS50eHQ=
+        let result = super::from_async_file(&path).await.unwrap().build();
+        assert_eq!(
+            result,
+            "\x1b]1337;File=size=7;name=aXRlcm0yaW1nX2Zyb21fYXN5bmNfZmlsZS50eHQ=:YWJjZGVmZw==\u{0007}"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
 }
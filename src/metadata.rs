@@ -0,0 +1,155 @@
+//! Lossless removal of metadata from JPEG and PNG data
+
+use alloc::vec::Vec;
+
+use crate::{detect_format, FileFormat};
+
+/// JPEG APP1 (EXIF, XMP), APP13 (IPTC) and COM segments
+const JPEG_METADATA_MARKERS: [u8; 3] = [0xe1, 0xed, 0xfe];
+
+/// PNG chunks holding EXIF, text and modification time
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+/// returns the data without metadata, or `None` if there is nothing to remove
+///
+/// Formats other than JPEG and PNG, and malformed data, are left unchanged.
+pub(crate) fn strip(data: &[u8]) -> Option<Vec<u8>> {
+    let stripped = match detect_format(data)? {
+        FileFormat::Jpeg => strip_jpeg(data)?,
+        FileFormat::Png => strip_png(data)?,
+        _ => return None,
+    };
+    (stripped.len() < data.len()).then_some(stripped)
+}
+
+fn strip_jpeg(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = data[..2].to_vec();
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        match marker {
+            // entropy-coded data follows the start of scan, and is copied as is
+            0xda | 0xd9 => {
+                out.extend_from_slice(&data[pos..]);
+                return Some(out);
+            }
+            0xff => {
+                // fill byte
+                out.push(0xff);
+                pos += 1;
+            }
+            0x01 | 0xd0..=0xd7 => {
+                out.extend_from_slice(&data[pos..pos + 2]);
+                pos += 2;
+            }
+            _ => {
+                let len = usize::from(u16::from_be_bytes([
+                    *data.get(pos + 2)?,
+                    *data.get(pos + 3)?,
+                ]));
+                let end = pos + 2 + len;
+                if len < 2 || end > data.len() {
+                    return None;
+                }
+                if !JPEG_METADATA_MARKERS.contains(&marker) {
+                    out.extend_from_slice(&data[pos..end]);
+                }
+                pos = end;
+            }
+        }
+    }
+}
+
+fn strip_png(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = data[..8].to_vec();
+    let mut pos = 8;
+    while pos < data.len() {
+        let len = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?);
+        let end = pos
+            .checked_add(12)?
+            .checked_add(usize::try_from(len).ok()?)?;
+        let chunk_type = data.get(pos + 4..pos + 8)?;
+        if end > data.len() {
+            return None;
+        }
+        if !PNG_METADATA_CHUNKS
+            .iter()
+            .any(|t| t.as_slice() == chunk_type)
+        {
+            out.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+        if chunk_type == b"IEND" {
+            out.extend_from_slice(&data[pos..]);
+            break;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(marker: u8, body: &[u8]) -> Vec<u8> {
+        let mut s = vec![0xff, marker];
+        s.extend_from_slice(&(body.len() as u16 + 2).to_be_bytes());
+        s.extend_from_slice(body);
+        s
+    }
+
+    fn chunk(chunk_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut c = (body.len() as u32).to_be_bytes().to_vec();
+        c.extend_from_slice(chunk_type);
+        c.extend_from_slice(body);
+        // the CRC is not checked
+        c.extend_from_slice(&[0; 4]);
+        c
+    }
+
+    #[test]
+    fn strip_jpeg() {
+        let jfif = segment(0xe0, b"JFIF\0\x01\x01");
+        let exif = segment(0xe1, b"Exif\0\0GPS");
+        let icc = segment(0xe2, b"ICC_PROFILE\0");
+        let comment = segment(0xfe, b"comment");
+        let scan = [0xff, 0xda, 0x00, 0x02, 0xe1, 0xff, 0x00, 0xff, 0xd9];
+        let data = [&[0xff, 0xd8][..], &jfif, &exif, &icc, &comment, &scan].concat();
+        let expected = [&[0xff, 0xd8][..], &jfif, &icc, &scan].concat();
+        assert_eq!(strip(&data), Some(expected));
+    }
+
+    #[test]
+    fn strip_png() {
+        let signature = b"\x89PNG\r\n\x1a\n";
+        let ihdr = chunk(b"IHDR", &[0; 13]);
+        let text = chunk(b"tEXt", b"Author\0someone");
+        let exif = chunk(b"eXIf", b"MM\0*");
+        let idat = chunk(b"IDAT", &[1, 2, 3]);
+        let iend = chunk(b"IEND", &[]);
+        let data = [&signature[..], &ihdr, &text, &idat, &exif, &iend].concat();
+        let expected = [&signature[..], &ihdr, &idat, &iend].concat();
+        assert_eq!(strip(&data), Some(expected));
+    }
+
+    #[test]
+    fn strip_nothing() {
+        let signature = b"\x89PNG\r\n\x1a\n";
+        let data = [
+            &signature[..],
+            &chunk(b"IHDR", &[0; 13]),
+            &chunk(b"IEND", &[]),
+        ]
+        .concat();
+        assert_eq!(strip(&data), None);
+        assert_eq!(strip(b"GIF89a"), None);
+        assert_eq!(strip(b"abc"), None);
+
+        // truncated segment
+        let data = [&[0xff, 0xd8][..], &segment(0xe1, b"Exif")[..5]].concat();
+        assert_eq!(strip(&data), None);
+    }
+}
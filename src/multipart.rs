@@ -0,0 +1,204 @@
+use std::io;
+
+use base64::Engine;
+
+use crate::{from_base64, Builder};
+
+/// returns multipart builder writing a file of `size` bytes to `w`
+///
+/// The file is sent as `MultipartFile`, followed by a `FilePart` for each part and `FileEnd`,
+/// so that a large image does not need to be sent in a single sequence.
+///
+/// # Examples
+///
+/// ```
+/// let mut buf = Vec::new();
+/// let mut multipart = iterm2img::multipart(&mut buf, 6).options(|b| b.inline(true));
+/// multipart.part("abc".as_bytes())?;
+/// multipart.part("def".as_bytes())?;
+/// multipart.finish()?;
+///
+/// let expected = concat!(
+///     "\x1b]1337;MultipartFile=size=6;inline=1\u{0007}",
+///     "\x1b]1337;FilePart=YWJj\u{0007}",
+///     "\x1b]1337;FilePart=ZGVm\u{0007}",
+///     "\x1b]1337;FileEnd\u{0007}",
+/// );
+/// assert_eq!(buf, expected.as_bytes());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn multipart<W: io::Write>(w: W, size: usize) -> MultipartBuilder<W> {
+    MultipartBuilder {
+        w,
+        options: from_base64(String::new(), size),
+        pending: Vec::new(),
+        started: false,
+    }
+}
+
+/// builder for multipart file transfer
+pub struct MultipartBuilder<W: io::Write> {
+    w: W,
+    options: Builder<'static>,
+    pending: Vec<u8>,
+    started: bool,
+}
+
+impl<W: io::Write> MultipartBuilder<W> {
+    /// set options of the file
+    ///
+    /// Must be called before the first part is written.
+    pub fn options(
+        mut self,
+        f: impl FnOnce(Builder<'static>) -> Builder<'static>,
+    ) -> MultipartBuilder<W> {
+        self.options = f(self.options);
+        self
+    }
+
+    /// write part of the file
+    ///
+    /// Bytes that do not fill a whole base64 group are kept until the next part.
+    pub fn part(&mut self, data: &[u8]) -> io::Result<()> {
+        self.start()?;
+        self.pending.extend_from_slice(data);
+        let len = self.pending.len() / 3 * 3;
+        if len == 0 {
+            return Ok(());
+        }
+        let encoded = self.options.engine().encode(&self.pending[..len]);
+        self.pending.drain(..len);
+        self.write_osc(&format!("FilePart={}", encoded))
+    }
+
+    /// write bytes read from the reader as parts of at most `part_len` bytes, flushing after each part
+    ///
+    /// Only one part is held in memory at a time, however large the file is.
+    /// Returns the number of bytes read.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `part_len` is 0.
+    pub fn copy_from(&mut self, mut r: impl io::Read, part_len: usize) -> io::Result<u64> {
+        assert!(part_len > 0, "part length must be non-zero");
+        let mut buf = vec![0; part_len];
+        let mut total = 0;
+        loop {
+            let n = match r.read(&mut buf) {
+                Ok(0) => return Ok(total),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.part(&buf[..n])?;
+            self.w.flush()?;
+            total += n as u64;
+        }
+    }
+
+    /// write remaining bytes and end the file, returning the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.start()?;
+        if !self.pending.is_empty() {
+            let encoded = self.options.engine().encode(&self.pending);
+            self.pending.clear();
+            self.write_osc(&format!("FilePart={}", encoded))?;
+        }
+        self.write_osc("FileEnd")?;
+        self.w.write_all(self.options.line_end().as_bytes())?;
+        self.w.flush()?;
+        Ok(self.w)
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        let header = format!("MultipartFile={}", self.options.osc_args());
+        self.write_osc(&header)
+    }
+
+    fn write_osc(&mut self, body: &str) -> io::Result<()> {
+        let mut framer = self.options.framer();
+        let mut out = Vec::new();
+        framer.start(&mut out);
+        framer.push(b"\x1b]1337;", &mut out);
+        framer.push(body.as_bytes(), &mut out);
+        framer.push(self.options.osc_terminator().as_bytes(), &mut out);
+        framer.finish(&mut out);
+        self.w.write_all(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Passthrough, Terminator};
+
+    #[test]
+    fn parts() {
+        let mut buf = Vec::new();
+        let mut multipart = multipart(&mut buf, 7).options(|b| b.name("xyz".to_string()));
+        multipart.part(b"ab").unwrap();
+        multipart.part(b"cdef").unwrap();
+        multipart.part(b"g").unwrap();
+        multipart.finish().unwrap();
+
+        // $ echo -n abcdefg | base64
+        // YWJjZGVmZw==
+        let expected = concat!(
+            "\x1b]1337;MultipartFile=size=7;name=eHl6\u{0007}",
+            "\x1b]1337;FilePart=YWJjZGVm\u{0007}",
+            "\x1b]1337;FilePart=Zw==\u{0007}",
+            "\x1b]1337;FileEnd\u{0007}",
+        );
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn copy_from() {
+        let mut buf = Vec::new();
+        let mut multipart = multipart(&mut buf, 7);
+        let n = multipart.copy_from(&b"abcdefg"[..], 4).unwrap();
+        multipart.finish().unwrap();
+        assert_eq!(n, 7);
+
+        let expected = concat!(
+            "\x1b]1337;MultipartFile=size=7\u{0007}",
+            "\x1b]1337;FilePart=YWJj\u{0007}",
+            "\x1b]1337;FilePart=ZGVm\u{0007}",
+            "\x1b]1337;FilePart=Zw==\u{0007}",
+            "\x1b]1337;FileEnd\u{0007}",
+        );
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn empty() {
+        let result = multipart(Vec::new(), 0).finish().unwrap();
+        let expected = concat!(
+            "\x1b]1337;MultipartFile=size=0\u{0007}",
+            "\x1b]1337;FileEnd\u{0007}",
+        );
+        assert_eq!(String::from_utf8(result).unwrap(), expected);
+    }
+
+    #[test]
+    fn options() {
+        let mut multipart = multipart(Vec::new(), 3).options(|b| {
+            b.terminator(Terminator::St)
+                .passthrough(Passthrough::Tmux)
+                .raw_mode(true)
+        });
+        multipart.part(b"abc").unwrap();
+        let result = multipart.finish().unwrap();
+        let expected = concat!(
+            "\x1bPtmux;\x1b\x1b]1337;MultipartFile=size=3\x1b\x1b\\\x1b\\",
+            "\x1bPtmux;\x1b\x1b]1337;FilePart=YWJj\x1b\x1b\\\x1b\\",
+            "\x1bPtmux;\x1b\x1b]1337;FileEnd\x1b\x1b\\\x1b\\",
+            "\r\n",
+        );
+        assert_eq!(String::from_utf8(result).unwrap(), expected);
+    }
+}
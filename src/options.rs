@@ -0,0 +1,136 @@
+use alloc::string::String;
+
+use crate::{from_slice, Builder, LengthUnit};
+
+/// reusable display options, separate from the payload
+///
+/// # Examples
+///
+/// ```
+/// let options = iterm2img::Options {
+///     width: Some(iterm2img::LengthUnit::Cell(10)),
+///     inline: Some(true),
+///     ..Default::default()
+/// };
+///
+/// let result = options.render("abc".as_bytes());
+/// assert_eq!(result, "\x1b]1337;File=size=3;width=10;inline=1:YWJj\u{0007}");
+///
+/// let result = options.render("xyz".as_bytes());
+/// assert_eq!(result, "\x1b]1337;File=size=3;width=10;inline=1:eHl6\u{0007}");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options {
+    /// filename
+    pub name: Option<String>,
+    /// width
+    pub width: Option<LengthUnit>,
+    /// height
+    pub height: Option<LengthUnit>,
+    /// preserve_aspect_ratio
+    pub preserve_aspect_ratio: Option<bool>,
+    /// inline
+    pub inline: Option<bool>,
+}
+
+impl Options {
+    /// returns empty options
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    /// build string from bytes with the options
+    pub fn render(&self, bytes: &[u8]) -> String {
+        self.apply(from_slice(bytes)).build()
+    }
+
+    /// set the options to the builder, overriding the options already set
+    pub fn apply<'a>(&self, mut builder: Builder<'a>) -> Builder<'a> {
+        if let Some(name) = &self.name {
+            builder = builder.name(name.clone());
+        }
+        builder.width = self.width.or(builder.width);
+        builder.height = self.height.or(builder.height);
+        builder.preserve_aspect_ratio =
+            self.preserve_aspect_ratio.or(builder.preserve_aspect_ratio);
+        builder.inline = self.inline.or(builder.inline);
+        builder
+    }
+}
+
+impl From<&Builder<'_>> for Options {
+    fn from(builder: &Builder<'_>) -> Self {
+        Options {
+            name: builder.get_name().map(String::from),
+            width: builder.width,
+            height: builder.height,
+            preserve_aspect_ratio: builder.preserve_aspect_ratio,
+            inline: builder.inline,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_bytes;
+
+    #[test]
+    fn render() {
+        let options = Options {
+            name: Some("xyz".to_string()),
+            height: Some(LengthUnit::Percent(50)),
+            preserve_aspect_ratio: Some(false),
+            ..Options::new()
+        };
+        assert_eq!(
+            options.render("abc".as_bytes()),
+            "\x1b]1337;File=size=3;name=eHl6;height=50%;preserve_aspect_ratio=0:YWJj\u{0007}"
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let options = Options {
+            width: Some(LengthUnit::Cell(5)),
+            ..Options::new()
+        };
+        let builder = from_bytes("abc".as_bytes()).width(10).inline(true);
+        assert_eq!(
+            options.apply(builder).build(),
+            "\x1b]1337;File=size=3;width=5;inline=1:YWJj\u{0007}"
+        );
+    }
+
+    #[test]
+    fn from_builder() {
+        let builder = from_bytes("abc".as_bytes()).name("xyz").width_auto();
+        let options = Options::from(&builder);
+        assert_eq!(options.name, Some("xyz".to_string()));
+        assert_eq!(options.width, Some(LengthUnit::Auto));
+        assert_eq!(options.render("abc".as_bytes()), builder.build());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let options = Options {
+            name: Some("xyz".to_string()),
+            width: Some(LengthUnit::Pixel(100)),
+            height: Some(LengthUnit::Auto),
+            inline: Some(true),
+            ..Options::new()
+        };
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"xyz","width":{"pixel":100},"height":"auto","preserve_aspect_ratio":null,"inline":true}"#
+        );
+        assert_eq!(serde_json::from_str::<Options>(&json).unwrap(), options);
+
+        let options: Options = serde_json::from_str(r#"{"width":{"cell":5}}"#).unwrap();
+        assert_eq!(options.width, Some(LengthUnit::Cell(5)));
+        assert_eq!(options.inline, None);
+    }
+}
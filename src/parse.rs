@@ -0,0 +1,215 @@
+//! Parse iTerm2 inline image sequences
+//!
+//! # Examples
+//!
+//! ```
+//! let sequence = iterm2img::from_bytes("abc".as_bytes())
+//!     .name("xyz")
+//!     .width(5)
+//!     .build();
+//!
+//! let parsed = iterm2img::parse::parse(&sequence)?;
+//! assert_eq!(parsed.options.size, Some(3));
+//! assert_eq!(parsed.options.name, Some("xyz".to_string()));
+//! assert_eq!(parsed.options.width, Some(iterm2img::LengthUnit::Cell(5)));
+//! assert_eq!(parsed.payload, "YWJj");
+//! assert_eq!(parsed.decode_payload()?, b"abc");
+//! # Ok::<(), iterm2img::Error>(())
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use base64::Engine;
+
+use crate::{Error, LengthUnit, BASE64_ENGINE};
+
+/// parsed `File` sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedImage {
+    /// arguments of the sequence
+    pub options: ParsedOptions,
+    /// base64-encoded payload
+    pub payload: String,
+}
+
+impl ParsedImage {
+    /// returns the original bytes of the payload
+    ///
+    /// Line breaks in the payload are ignored, and padding is optional.
+    pub fn decode_payload(&self) -> Result<Vec<u8>, Error> {
+        let engine = base64::engine::GeneralPurpose::new(
+            &base64::alphabet::STANDARD,
+            base64::engine::GeneralPurposeConfig::new()
+                .with_decode_padding_mode(base64::engine::DecodePaddingMode::Indifferent),
+        );
+        let payload: String = self
+            .payload
+            .chars()
+            .filter(|c| !matches!(c, '\r' | '\n'))
+            .collect();
+        Ok(engine.decode(payload)?)
+    }
+}
+
+/// arguments of a parsed `File` sequence
+///
+/// Unknown keys are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedOptions {
+    /// size
+    pub size: Option<usize>,
+    /// filename, base64-decoded if possible
+    pub name: Option<String>,
+    /// width
+    pub width: Option<LengthUnit>,
+    /// height
+    pub height: Option<LengthUnit>,
+    /// preserveAspectRatio
+    pub preserve_aspect_ratio: Option<bool>,
+    /// inline
+    pub inline: Option<bool>,
+    /// doNotMoveCursor
+    pub do_not_move_cursor: Option<bool>,
+}
+
+/// parses `ESC ]1337;File=[args]:[payload]` terminated by BEL or ST
+pub fn parse(s: &str) -> Result<ParsedImage, Error> {
+    let body = s
+        .strip_prefix("\x1b]1337;File=")
+        .and_then(|s| {
+            s.strip_suffix('\u{0007}')
+                .or_else(|| s.strip_suffix("\x1b\\"))
+        })
+        .ok_or(Error::InvalidSequence)?;
+    let (args, payload) = body.split_once(':').ok_or(Error::InvalidSequence)?;
+
+    let mut options = ParsedOptions::default();
+    for arg in args.split(';').filter(|a| !a.is_empty()) {
+        let (key, value) = arg.split_once('=').ok_or(Error::InvalidSequence)?;
+        match key {
+            "size" => options.size = Some(value.parse().map_err(|_| Error::InvalidSequence)?),
+            "name" => options.name = Some(parse_name(value)),
+            "width" => options.width = Some(parse_length(value)?),
+            "height" => options.height = Some(parse_length(value)?),
+            "preserveAspectRatio" | "preserve_aspect_ratio" => {
+                options.preserve_aspect_ratio = Some(parse_bool(value)?)
+            }
+            "inline" => options.inline = Some(parse_bool(value)?),
+            "doNotMoveCursor" => options.do_not_move_cursor = Some(parse_bool(value)?),
+            _ => {}
+        }
+    }
+
+    Ok(ParsedImage {
+        options,
+        payload: payload.to_string(),
+    })
+}
+
+fn parse_name(value: &str) -> String {
+    BASE64_ENGINE
+        .decode(value)
+        .ok()
+        .and_then(|b| String::from_utf8(b).ok())
+        .unwrap_or_else(|| value.to_string())
+}
+
+fn parse_length(value: &str) -> Result<LengthUnit, Error> {
+    value.parse().map_err(|_| Error::InvalidSequence)
+}
+
+fn parse_bool(value: &str) -> Result<bool, Error> {
+    match value {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        _ => Err(Error::InvalidSequence),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_bytes, Terminator};
+
+    #[test]
+    fn parse_all_options() {
+        let sequence = from_bytes("abcdefg".as_bytes())
+            .name("xyz".to_string())
+            .width_px(100)
+            .height_percent(50)
+            .preserve_aspect_ratio(false)
+            .inline(true)
+            .do_not_move_cursor(true)
+            .terminator(Terminator::St)
+            .build();
+        let parsed = parse(&sequence).unwrap();
+        assert_eq!(
+            parsed,
+            ParsedImage {
+                options: ParsedOptions {
+                    size: Some(7),
+                    name: Some("xyz".to_string()),
+                    width: Some(LengthUnit::Pixel(100)),
+                    height: Some(LengthUnit::Percent(50)),
+                    preserve_aspect_ratio: Some(false),
+                    inline: Some(true),
+                    do_not_move_cursor: Some(true),
+                },
+                payload: "YWJjZGVmZw==".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_spec_keys() {
+        let parsed =
+            parse("\x1b]1337;File=width=auto;preserveAspectRatio=1;unknown=x:YWJj\u{0007}")
+                .unwrap();
+        assert_eq!(parsed.options.width, Some(LengthUnit::Auto));
+        assert_eq!(parsed.options.preserve_aspect_ratio, Some(true));
+        assert_eq!(parsed.options.size, None);
+
+        let parsed = parse("\x1b]1337;File=:\u{0007}").unwrap();
+        assert_eq!(parsed.options, ParsedOptions::default());
+        assert_eq!(parsed.payload, "");
+    }
+
+    #[test]
+    fn decode_payload() {
+        let bytes: Vec<u8> = (0..1000).map(|i| (i % 256) as u8).collect();
+        for sequence in [
+            from_bytes(bytes.clone()).build(),
+            from_bytes(bytes.clone()).line_width(76).build(),
+            from_bytes(bytes.clone())
+                .base64_engine(base64::engine::general_purpose::STANDARD_NO_PAD)
+                .build(),
+        ] {
+            let parsed = parse(&sequence).unwrap();
+            assert_eq!(parsed.decode_payload().unwrap(), bytes);
+        }
+
+        let parsed = parse("\x1b]1337;File=:YW!j\u{0007}").unwrap();
+        assert!(matches!(
+            parsed.decode_payload(),
+            Err(Error::InvalidBase64(_))
+        ));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for s in [
+            "",
+            "\x1b]1337;File=size=3:YWJj",
+            "\x1b]1337;File=size=3\u{0007}",
+            "\x1b]1337;File=size=a:YWJj\u{0007}",
+            "\x1b]1337;File=width=5em:YWJj\u{0007}",
+            "\x1b]1337;File=inline=yes:YWJj\u{0007}",
+            "\x1b]1337;File=inline:YWJj\u{0007}",
+        ] {
+            assert!(matches!(parse(s), Err(Error::InvalidSequence)), "{:?}", s);
+        }
+    }
+}
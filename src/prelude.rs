@@ -0,0 +1,23 @@
+//! Commonly used types for glob import
+//!
+//! # Examples
+//!
+//! ```
+//! use iterm2img::prelude::*;
+//!
+//! let result = iterm2img::from_bytes("abc".as_bytes())
+//!     .size(Dimensions::new(LengthUnit::Cell(10), LengthUnit::Auto))
+//!     .terminator(Terminator::St)
+//!     .build();
+//! assert_eq!(result, "\x1b]1337;File=size=3;width=10;height=auto:YWJj\x1b\\");
+//! ```
+
+#[cfg(feature = "crossterm")]
+pub use crate::DisplayImage;
+#[cfg(feature = "ratatui")]
+pub use crate::InlineImage;
+pub use crate::{
+    Attention, Backend, Batch, Builder, Control, Dimension, Dimensions, DisplayConfig, Error,
+    FileFormat, LengthUnit, Options, Passthrough, Sequence, TerminalProfile, Terminator,
+    ToInlineImage,
+};
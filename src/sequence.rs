@@ -0,0 +1,83 @@
+use alloc::string::String;
+use core::fmt;
+
+/// built sequence split into its parts
+///
+/// Created by [`Builder::sequence`](crate::Builder::sequence).
+///
+/// # Examples
+///
+/// ```
+/// let sequence = iterm2img::from_bytes("abc".as_bytes()).width(5).sequence();
+///
+/// assert_eq!(sequence.header(), "\x1b]1337;File=size=3;width=5:");
+/// assert_eq!(sequence.payload(), "YWJj");
+/// assert_eq!(sequence.terminator(), "\u{0007}");
+/// assert_eq!(sequence.to_string(), "\x1b]1337;File=size=3;width=5:YWJj\u{0007}");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sequence {
+    header: String,
+    payload: String,
+    terminator: String,
+}
+
+impl Sequence {
+    pub(crate) fn new(header: String, payload: String, terminator: String) -> Sequence {
+        Sequence {
+            header,
+            payload,
+            terminator,
+        }
+    }
+
+    /// returns header, from `ESC ]1337;File=` to `:` before the payload
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// returns base64-encoded payload
+    pub fn payload(&self) -> &str {
+        &self.payload
+    }
+
+    /// returns terminator after the payload
+    pub fn terminator(&self) -> &str {
+        &self.terminator
+    }
+}
+
+impl fmt::Display for Sequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.header)?;
+        f.write_str(&self.payload)?;
+        f.write_str(&self.terminator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_bytes, Terminator};
+
+    #[test]
+    fn sequence() {
+        let builder = from_bytes("abcdefg".as_bytes())
+            .name("xyz".to_string())
+            .terminator(Terminator::St);
+        let sequence = builder.sequence();
+        assert_eq!(sequence.header(), "\x1b]1337;File=size=7;name=eHl6:");
+        assert_eq!(sequence.payload(), "YWJjZGVmZw==");
+        assert_eq!(sequence.terminator(), "\x1b\\");
+        assert_eq!(sequence.to_string(), builder.build());
+    }
+
+    #[test]
+    fn sequence_unwrapped() {
+        let builder = from_bytes("abcdefg".as_bytes())
+            .tmux_passthrough(true)
+            .raw_mode(true);
+        let sequence = builder.sequence();
+        assert_eq!(sequence.header(), "\x1b]1337;File=size=7:");
+        assert_eq!(sequence.terminator(), "\u{0007}");
+    }
+}
@@ -0,0 +1,204 @@
+use image::{imageops::FilterType, RgbaImage};
+
+use crate::{transform::MAX_CANVAS_PIXELS, Error};
+
+/// number of levels per channel of the palette, 6x6x6 colors in total
+const LEVELS: u32 = 6;
+
+/// band entry of a transparent pixel, outside the palette
+const NO_COLOR: u8 = u8::MAX;
+
+/// encodes decodable image data into a Sixel sequence
+///
+/// The image is resized when `width` or `height` in pixels is given, keeping the aspect ratio if only one is given.
+/// Returns `Error::EncodeFailure` if the image cannot be decoded,
+/// and `Error::InvalidImageSize` if the resized image would exceed `MAX_CANVAS_PIXELS`.
+pub(crate) fn encode(
+    data: &[u8],
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<String, Error> {
+    let image = image::load_from_memory(data)
+        .map_err(|_| Error::EncodeFailure("payload cannot be decoded as an image"))?;
+    let (w, h) = (u64::from(image.width()), u64::from(image.height()));
+    let scale = |len: u64, from: u64, to: u32| (len * u64::from(to) / from.max(1)).max(1);
+    let (target_w, target_h) = match (width, height) {
+        (None, None) => (w, h),
+        (Some(tw), Some(th)) => (u64::from(tw), u64::from(th)),
+        (Some(tw), None) => (u64::from(tw), scale(h, w, tw)),
+        (None, Some(th)) => (scale(w, h, th), u64::from(th)),
+    };
+    if target_w.saturating_mul(target_h) > MAX_CANVAS_PIXELS {
+        let clamp = |v: u64| u32::try_from(v).unwrap_or(u32::MAX);
+        return Err(Error::InvalidImageSize {
+            width: clamp(target_w),
+            height: clamp(target_h),
+        });
+    }
+    let image = match (width, height) {
+        (None, None) => image,
+        (Some(w), Some(h)) => image.resize_exact(w, h, FilterType::Triangle),
+        (Some(w), None) => image.resize(w, u32::MAX, FilterType::Triangle),
+        (None, Some(h)) => image.resize(u32::MAX, h, FilterType::Triangle),
+    };
+    Ok(encode_rgba(&image.into_rgba8()))
+}
+
+fn encode_rgba(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let colors = (LEVELS * LEVELS * LEVELS) as usize;
+
+    // P2=1 leaves transparent pixels unchanged
+    let mut s = format!("\x1bP0;1;q\"1;1;{};{}", width, height);
+
+    let mut used = vec![false; colors];
+    for p in image.pixels() {
+        if let Some(c) = color_index(p.0) {
+            used[c] = true;
+        }
+    }
+    for (c, _) in used.iter().enumerate().filter(|(_, u)| **u) {
+        let [r, g, b] = palette_rgb(c);
+        s.push_str(&format!("#{};2;{};{};{}", c, r, g, b));
+    }
+
+    // colors of one band and the sixels of one color, so that memory stays linear in the width
+    let w = width as usize;
+    let mut band = vec![NO_COLOR; w * height.min(6) as usize];
+    let mut sixels = vec![0u8; w];
+    for y0 in (0..height).step_by(6) {
+        let rows = ((y0 + 6).min(height) - y0) as usize;
+        let mut seen = vec![false; colors];
+        let mut colors_in_band = Vec::new();
+        for dy in 0..rows {
+            for x in 0..width {
+                let c = color_index(image.get_pixel(x, y0 + dy as u32).0);
+                if let Some(c) = c {
+                    if !seen[c] {
+                        seen[c] = true;
+                        colors_in_band.push(c);
+                    }
+                }
+                band[dy * w + x as usize] = c.map_or(NO_COLOR, |c| c as u8);
+            }
+        }
+        for c in colors_in_band {
+            sixels.fill(0);
+            for dy in 0..rows {
+                for (sixel, b) in sixels.iter_mut().zip(&band[dy * w..(dy + 1) * w]) {
+                    if usize::from(*b) == c {
+                        *sixel |= 1 << dy;
+                    }
+                }
+            }
+            s.push_str(&format!("#{}", c));
+            push_run_length(&mut s, &sixels);
+            s.push('$');
+        }
+        if y0 + 6 < height {
+            s.push('-');
+        }
+    }
+
+    s.push_str("\x1b\\");
+    s
+}
+
+fn color_index([r, g, b, a]: [u8; 4]) -> Option<usize> {
+    if a < 128 {
+        return None;
+    }
+    let level = |v: u8| (u32::from(v) * (LEVELS - 1) + 127) / 255;
+    Some((level(r) * LEVELS * LEVELS + level(g) * LEVELS + level(b)) as usize)
+}
+
+/// returns the color of the palette in percent as Sixel specifies
+fn palette_rgb(c: usize) -> [u32; 3] {
+    let c = c as u32;
+    let percent = |level: u32| level * 100 / (LEVELS - 1);
+    [
+        percent(c / (LEVELS * LEVELS)),
+        percent(c / LEVELS % LEVELS),
+        percent(c % LEVELS),
+    ]
+}
+
+fn push_run_length(s: &mut String, sixels: &[u8]) {
+    let mut iter = sixels.iter().peekable();
+    while let Some(&b) = iter.next() {
+        let mut n = 1;
+        while iter.next_if_eq(&&b).is_some() {
+            n += 1;
+        }
+        let ch = char::from(63 + b);
+        if n > 3 {
+            s.push_str(&format!("!{}{}", n, ch));
+        } else {
+            (0..n).for_each(|_| s.push(ch));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_pixels() {
+        // red, transparent, blue on the first row and red on the second
+        let pixels = [
+            [255, 0, 0, 255],
+            [0, 0, 0, 0],
+            [0, 0, 255, 255],
+            [255, 0, 0, 255],
+            [255, 0, 0, 255],
+            [255, 0, 0, 255],
+        ];
+        let image = RgbaImage::from_raw(3, 2, pixels.concat()).unwrap();
+        assert_eq!(
+            encode_rgba(&image),
+            "\x1bP0;1;q\"1;1;3;2#5;2;0;0;100#180;2;100;0;0#180BAA$#5??@$\x1b\\"
+        );
+    }
+
+    #[test]
+    fn encode_bands_and_runs() {
+        let image = RgbaImage::from_pixel(5, 7, image::Rgba([255, 255, 255, 255]));
+        assert_eq!(
+            encode_rgba(&image),
+            "\x1bP0;1;q\"1;1;5;7#215;2;100;100;100#215!5~$-#215!5@$\x1b\\"
+        );
+    }
+
+    #[test]
+    fn encode_invalid() {
+        assert!(matches!(
+            encode(b"not an image", None, None),
+            Err(Error::EncodeFailure(_))
+        ));
+    }
+
+    #[test]
+    fn encode_too_large() {
+        let png = crate::png::encode_rgba(2, 1, &[255; 8]);
+        assert!(matches!(
+            encode(&png, Some(u32::MAX), None),
+            Err(Error::InvalidImageSize {
+                width: u32::MAX,
+                height: 2147483647,
+            })
+        ));
+        assert!(matches!(
+            encode(&png, Some(1 << 13), Some(1 << 14)),
+            Err(Error::InvalidImageSize { .. })
+        ));
+    }
+
+    #[test]
+    fn encode_resize() {
+        let pixels = [255; 4 * 4 * 2];
+        let png = crate::png::encode_rgba(4, 2, &pixels);
+        let result = encode(&png, Some(2), None).unwrap();
+        assert!(result.starts_with("\x1bP0;1;q\"1;1;2;1#"));
+    }
+}
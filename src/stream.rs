@@ -0,0 +1,348 @@
+use alloc::{string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{framing::Framer, wrap_lines, Builder, Payload, ENCODE_CHUNK_LEN};
+
+/// lazily produces the sequence in framed pieces
+pub(crate) struct Pieces<'b> {
+    builder: &'b Builder<'b>,
+    framer: Framer,
+    state: State,
+    pos: usize,
+    column: usize,
+    scratch: Vec<u8>,
+    backend: Option<Option<(String, String)>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    Payload,
+    Trailer,
+    Done,
+}
+
+impl<'b> Pieces<'b> {
+    pub(crate) fn new(builder: &'b Builder<'_>) -> Pieces<'b> {
+        Pieces {
+            builder,
+            framer: builder.framer(),
+            state: State::Header,
+            pos: 0,
+            column: 0,
+            scratch: Vec::new(),
+            backend: None,
+        }
+    }
+
+    /// uses `backend` already returned by `backend_sequence` instead of encoding it again
+    pub(crate) fn with_backend(
+        builder: &'b Builder<'_>,
+        backend: Option<(String, String)>,
+    ) -> Pieces<'b> {
+        Pieces {
+            backend: Some(backend),
+            ..Pieces::new(builder)
+        }
+    }
+
+    /// appends the next piece to `out`, returns false at the end
+    pub(crate) fn next_into(&mut self, out: &mut Vec<u8>) -> bool {
+        match self.state {
+            State::Header => {
+                let backend = match self.backend.take() {
+                    Some(backend) => Ok(backend),
+                    None => self.builder.backend_sequence(),
+                };
+                if let Ok(Some((sequence, text))) = &backend {
+                    if sequence.is_empty() {
+                        // plain text is shown by the multiplexer itself
+                        out.extend_from_slice(self.builder.hyperlink_start().as_bytes());
+                        out.extend_from_slice(text.as_bytes());
+                        out.extend_from_slice(self.builder.hyperlink_end().as_bytes());
+                        out.extend_from_slice(self.builder.line_end().as_bytes());
+                        self.state = State::Done;
+                        return true;
+                    }
+                }
+                self.framer.start(out);
+                self.framer
+                    .push(self.builder.hyperlink_start().as_bytes(), out);
+                if let Ok(Some((sequence, text))) = backend {
+                    self.framer.push(sequence.as_bytes(), out);
+                    self.framer
+                        .push(self.builder.hyperlink_end().as_bytes(), out);
+                    self.framer.finish(out);
+                    out.extend_from_slice(text.as_bytes());
+                    out.extend_from_slice(self.builder.line_end().as_bytes());
+                    self.state = State::Done;
+                    return true;
+                }
+                self.framer.push(self.builder.osc_header().as_bytes(), out);
+                self.state = State::Payload;
+            }
+            State::Payload => {
+                if !self.next_payload_into(out) {
+                    self.state = State::Trailer;
+                    return self.next_into(out);
+                }
+            }
+            State::Trailer => {
+                self.framer
+                    .push(self.builder.osc_terminator().as_bytes(), out);
+                self.framer
+                    .push(self.builder.hyperlink_end().as_bytes(), out);
+                self.framer.finish(out);
+                out.extend_from_slice(self.builder.line_end().as_bytes());
+                self.state = State::Done;
+            }
+            State::Done => return false,
+        }
+        true
+    }
+
+    /// appends all remaining pieces to `out`
+    pub(crate) fn collect_into(mut self, out: &mut Vec<u8>) {
+        while self.next_into(out) {}
+    }
+
+    fn next_payload_into(&mut self, out: &mut Vec<u8>) -> bool {
+        // base64 is encoded straight into `out` unless it has to be wrapped or framed
+        let direct = self.builder.line_width.unwrap_or(0) == 0 && !self.framer.is_active();
+        let target = if direct { &mut *out } else { &mut self.scratch };
+        let start = target.len();
+        match &self.builder.payload {
+            Payload::Base64 { encoded, .. } => {
+                let end = encoded.len().min(self.pos + ENCODE_CHUNK_LEN / 3 * 4);
+                target.extend_from_slice(&encoded.as_bytes()[self.pos..end]);
+                self.pos = end;
+            }
+            payload => {
+                let bytes = payload.bytes().unwrap_or_default();
+                let end = bytes.len().min(self.pos + ENCODE_CHUNK_LEN);
+                self.builder.encode_into(&bytes[self.pos..end], target);
+                self.pos = end;
+            }
+        }
+        if target.len() == start {
+            return false;
+        }
+        if !direct {
+            self.flush_scratch(out);
+        }
+        true
+    }
+
+    /// appends `bytes` as the next part of a payload read from elsewhere
+    ///
+    /// Must be called after the header, with a multiple of 3 bytes except for the last part.
+    #[cfg(feature = "std")]
+    fn push_payload(&mut self, bytes: &[u8], out: &mut Vec<u8>) {
+        let direct = self.builder.line_width.unwrap_or(0) == 0 && !self.framer.is_active();
+        if direct {
+            self.builder.encode_into(bytes, out);
+        } else {
+            self.builder.encode_into(bytes, &mut self.scratch);
+            self.flush_scratch(out);
+        }
+    }
+
+    fn flush_scratch(&mut self, out: &mut Vec<u8>) {
+        let mut wrapped = Vec::with_capacity(self.scratch.len());
+        wrap_lines(
+            &self.scratch,
+            self.builder.line_width,
+            &mut self.column,
+            &mut wrapped,
+        );
+        self.framer.push(&wrapped, out);
+        self.scratch.clear();
+    }
+}
+
+/// writes the sequence of `builder` to `w` with the payload read from `r` in chunks
+///
+/// The payload of `builder` must be empty and its backend must be iTerm2.
+#[cfg(feature = "std")]
+pub(crate) fn copy_encoded(
+    builder: &Builder<'_>,
+    mut r: impl io::Read,
+    w: &mut impl io::Write,
+) -> io::Result<()> {
+    let mut pieces = Pieces::new(builder);
+    let mut out = Vec::new();
+    pieces.next_into(&mut out);
+    let mut buf = vec![0; ENCODE_CHUNK_LEN];
+    loop {
+        // short reads are filled up so that only the last chunk is padded
+        let mut n = 0;
+        while n < buf.len() {
+            match r.read(&mut buf[n..]) {
+                Ok(0) => break,
+                Ok(len) => n += len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        pieces.push_payload(&buf[..n], &mut out);
+        w.write_all(&out)?;
+        out.clear();
+        if n < buf.len() {
+            break;
+        }
+    }
+    pieces.collect_into(&mut out);
+    w.write_all(&out)
+}
+
+/// lazily produces sequence bytes into arbitrary sized buffers
+struct Encoder<'b> {
+    pieces: Pieces<'b>,
+    current: Vec<u8>,
+    pos: usize,
+}
+
+impl<'b> Encoder<'b> {
+    fn new(builder: &'b Builder<'_>) -> Encoder<'b> {
+        Encoder {
+            pieces: Pieces::new(builder),
+            current: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// fills `out` with the next bytes and returns the number of bytes written, 0 means the end
+    fn fill(&mut self, out: &mut [u8]) -> usize {
+        let mut n = 0;
+        while n < out.len() {
+            if self.pos == self.current.len() {
+                self.current.clear();
+                self.pos = 0;
+                if !self.pieces.next_into(&mut self.current) {
+                    break;
+                }
+            }
+            let len = (self.current.len() - self.pos).min(out.len() - n);
+            out[n..n + len].copy_from_slice(&self.current[self.pos..self.pos + len]);
+            self.pos += len;
+            n += len;
+        }
+        n
+    }
+}
+
+/// iterator over a sequence split into chunks
+///
+/// Created by [`Builder::chunks`].
+pub struct Chunks<'b> {
+    encoder: Encoder<'b>,
+    size: usize,
+}
+
+impl<'b> Chunks<'b> {
+    pub(crate) fn new(builder: &'b Builder<'_>, size: usize) -> Chunks<'b> {
+        assert!(size > 0, "chunk size must be non-zero");
+        Chunks {
+            encoder: Encoder::new(builder),
+            size,
+        }
+    }
+}
+
+impl Iterator for Chunks<'_> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = vec![0; self.size];
+        let n = self.encoder.fill(&mut chunk);
+        if n == 0 {
+            return None;
+        }
+        chunk.truncate(n);
+        Some(chunk)
+    }
+}
+
+/// reader over a sequence
+///
+/// Created by [`Builder::reader`].
+#[cfg(feature = "std")]
+pub struct SequenceReader<'b> {
+    encoder: Encoder<'b>,
+}
+
+#[cfg(feature = "std")]
+impl<'b> SequenceReader<'b> {
+    pub(crate) fn new(builder: &'b Builder<'_>) -> SequenceReader<'b> {
+        SequenceReader {
+            encoder: Encoder::new(builder),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Read for SequenceReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.encoder.fill(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use std::io::{self, Read};
+
+    use crate::{from_base64, from_bytes};
+
+    #[test]
+    fn chunks() {
+        let bytes: Vec<u8> = (0..1000).map(|i| (i % 256) as u8).collect();
+        for builder in [
+            from_bytes(bytes.clone()).width(10),
+            from_bytes(bytes.clone()).raw_mode(true),
+            from_bytes(bytes.clone()).terminator(crate::Terminator::St),
+            from_bytes(bytes.clone()).tmux_passthrough(true),
+            from_bytes(bytes.clone()).passthrough(crate::Passthrough::Screen),
+            from_bytes(bytes.clone()).line_width(76),
+            from_bytes(bytes.clone())
+                .base64_engine(base64::engine::general_purpose::URL_SAFE_NO_PAD),
+            from_bytes(bytes.clone())
+                .tmux_passthrough(true)
+                .passthrough_chunk_len(100),
+            from_bytes(Vec::new()),
+            from_base64(crate::to_base64_str(&bytes), bytes.len()),
+        ] {
+            let expected = builder.to_string().into_bytes();
+            for size in [1, 2, 3, 4, 5, 7, 64, 100000] {
+                let chunks: Vec<Vec<u8>> = builder.chunks(size).collect();
+                assert!(chunks.iter().all(|c| !c.is_empty() && c.len() <= size));
+                assert!(chunks[..chunks.len() - 1].iter().all(|c| c.len() == size));
+                assert_eq!(chunks.concat(), expected, "size={}", size);
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reader() {
+        let bytes: Vec<u8> = (0..100000).map(|i| (i % 256) as u8).collect();
+        let builder = from_bytes(bytes).name("xyz".to_string());
+
+        let mut buf = Vec::new();
+        io::copy(&mut builder.reader(), &mut buf).unwrap();
+        assert_eq!(buf, builder.to_string().into_bytes());
+
+        let mut reader = builder.reader();
+        let mut small = [0; 5];
+        assert_eq!(reader.read(&mut small).unwrap(), 5);
+        assert_eq!(&small, b"\x1b]133");
+    }
+
+    #[test]
+    #[should_panic]
+    fn chunks_zero() {
+        let builder = from_bytes(Vec::new());
+        let _ = builder.chunks(0);
+    }
+}
@@ -0,0 +1,54 @@
+use resvg::{tiny_skia, usvg};
+
+use crate::{png, Error};
+
+/// rasterizes svg into a PNG of exactly `width` x `height` pixels
+pub(crate) fn rasterize(svg: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Error> {
+    let tree = usvg::Tree::from_data(svg, &usvg::Options::default())?;
+    let mut pixmap =
+        tiny_skia::Pixmap::new(width, height).ok_or(Error::InvalidImageSize { width, height })?;
+
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        width as f32 / size.width(),
+        height as f32 / size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    let pixels: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    Ok(png::encode_rgba(width, height, &pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+        <rect width="10" height="10" fill="red"/>
+    </svg>"#;
+
+    #[test]
+    fn rasterize_size() {
+        let png = rasterize(SVG, 40, 20).unwrap();
+        assert_eq!(&png[16..24], [0, 0, 0, 40, 0, 0, 0, 20]);
+    }
+
+    #[test]
+    fn rasterize_invalid() {
+        assert!(matches!(rasterize(b"<svg", 10, 10), Err(Error::Svg(_))));
+        assert!(matches!(
+            rasterize(SVG, 0, 10),
+            Err(Error::InvalidImageSize {
+                width: 0,
+                height: 10
+            })
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+use base64::Engine;
+use termwiz::escape::{
+    osc::{ITermDimension, ITermFileData, ITermProprietary},
+    Action, OperatingSystemCommand,
+};
+
+use crate::{Builder, Error, LengthUnit, Payload, BASE64_ENGINE};
+
+impl TryFrom<&Builder<'_>> for ITermFileData {
+    type Error = Error;
+
+    fn try_from(builder: &Builder<'_>) -> Result<Self, Self::Error> {
+        let data = match &builder.payload {
+            Payload::Base64 { encoded, .. } => builder.engine().decode(encoded)?,
+            payload => payload.bytes().unwrap_or_default().to_vec(),
+        };
+        // termwiz encodes the name itself, so a raw name is decoded if possible
+        let name = builder.name.as_ref().map(|name| {
+            let lossy = || String::from_utf8_lossy(name).into_owned();
+            if builder.raw_name != Some(true) {
+                return lossy();
+            }
+            BASE64_ENGINE
+                .decode(name)
+                .ok()
+                .and_then(|b| String::from_utf8(b).ok())
+                .unwrap_or_else(lossy)
+        });
+        Ok(ITermFileData {
+            name,
+            size: (builder.size_hint != Some(false)).then(|| builder.payload.len()),
+            width: builder
+                .clamped_width()
+                .map_or(ITermDimension::Automatic, to_dimension),
+            height: builder
+                .clamped_height()
+                .map_or(ITermDimension::Automatic, to_dimension),
+            preserve_aspect_ratio: builder.preserve_aspect_ratio.unwrap_or(true),
+            inline: builder.inline.unwrap_or(false),
+            do_not_move_cursor: builder.do_not_move_cursor.unwrap_or(false),
+            data,
+        })
+    }
+}
+
+impl TryFrom<&Builder<'_>> for OperatingSystemCommand {
+    type Error = Error;
+
+    fn try_from(builder: &Builder<'_>) -> Result<Self, Self::Error> {
+        let data = ITermFileData::try_from(builder)?;
+        Ok(OperatingSystemCommand::ITermProprietary(
+            ITermProprietary::File(Box::new(data)),
+        ))
+    }
+}
+
+impl TryFrom<&Builder<'_>> for Action {
+    type Error = Error;
+
+    fn try_from(builder: &Builder<'_>) -> Result<Self, Self::Error> {
+        let osc = OperatingSystemCommand::try_from(builder)?;
+        Ok(Action::OperatingSystemCommand(Box::new(osc)))
+    }
+}
+
+fn to_dimension(unit: LengthUnit) -> ITermDimension {
+    let clamp = |v: u64| i64::try_from(v).unwrap_or(i64::MAX);
+    match unit {
+        LengthUnit::Cell(v) => ITermDimension::Cells(clamp(v)),
+        LengthUnit::Pixel(v) => ITermDimension::Pixels(clamp(v)),
+        LengthUnit::Percent(v) => ITermDimension::Percent(clamp(v)),
+        LengthUnit::Auto => ITermDimension::Automatic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_base64, from_bytes};
+
+    #[test]
+    fn file_data() {
+        let builder = from_bytes("abcdefg".as_bytes())
+            .name("xyz".to_string())
+            .width(10)
+            .height_px(200)
+            .preserve_aspect_ratio(false)
+            .inline(true);
+        let data = ITermFileData::try_from(&builder).unwrap();
+        assert_eq!(data.name.as_deref(), Some("xyz"));
+        assert_eq!(data.size, Some(7));
+        assert_eq!(data.width, ITermDimension::Cells(10));
+        assert_eq!(data.height, ITermDimension::Pixels(200));
+        assert!(!data.preserve_aspect_ratio);
+        assert!(data.inline);
+        assert_eq!(data.data, b"abcdefg");
+
+        let builder = from_base64("YWJjZGVmZw==".to_string(), 7).width_percent(50);
+        let data = ITermFileData::try_from(&builder).unwrap();
+        assert_eq!(data.name, None);
+        assert_eq!(data.width, ITermDimension::Percent(50));
+        assert_eq!(data.height, ITermDimension::Automatic);
+        assert!(data.preserve_aspect_ratio);
+        assert!(!data.inline);
+        assert_eq!(data.data, b"abcdefg");
+
+        let builder = from_bytes(Vec::new())
+            .name("eHl6".to_string())
+            .raw_name(true);
+        let data = ITermFileData::try_from(&builder).unwrap();
+        assert_eq!(data.name.as_deref(), Some("xyz"));
+    }
+
+    #[test]
+    fn action() {
+        let builder = from_bytes("abcdefg".as_bytes()).inline(true);
+        let action = Action::try_from(&builder).unwrap();
+        // termwiz terminates OSC with ST
+        assert_eq!(
+            action.to_string(),
+            "\x1b]1337;File=size=7;inline=1:YWJjZGVmZw==\x1b\\"
+        );
+    }
+
+    #[test]
+    fn invalid_base64() {
+        let builder = from_base64("!!!!".to_string(), 3);
+        let result = Action::try_from(&builder);
+        assert!(matches!(result, Err(Error::InvalidBase64(_))));
+    }
+}
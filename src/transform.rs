@@ -0,0 +1,811 @@
+use std::io;
+
+use image::{
+    codecs::jpeg::JpegEncoder,
+    imageops::{self, FilterType},
+    metadata::Orientation,
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage, Rgba, RgbaImage,
+};
+
+#[cfg(feature = "terminal_size")]
+use crate::CellSize;
+use crate::{detect_format, from_bytes, Builder, Error, FileFormat};
+
+const JPEG_QUALITIES: [u8; 4] = [85, 70, 55, 40];
+const THUMBNAIL_QUALITY: u8 = 75;
+
+/// largest canvas created by the border, padding and Sixel resizing, 256 MiB of RGBA
+pub(crate) const MAX_CANVAS_PIXELS: u64 = 1 << 26;
+
+/// image format to convert payloads to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// PNG, lossless
+    Png,
+    /// JPEG, lossy and without transparency
+    Jpeg,
+}
+
+impl Format {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Format::Png => ImageFormat::Png,
+            Format::Jpeg => ImageFormat::Jpeg,
+        }
+    }
+}
+
+/// color transparent pixels are flattened onto by [`Builder::background`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    /// the given RGB color
+    Rgb([u8; 3]),
+    /// white for dark images and black for light images, judged by the average brightness
+    Auto,
+}
+
+impl Builder<'_> {
+    /// re-encodes the image until the payload fits in `limit` bytes
+    ///
+    /// The limit is compared against the payload before base64 encoding, like `max_payload_len`.
+    /// If the payload already fits, it is kept as is.
+    /// Otherwise the image is encoded as PNG and, if it has no alpha channel, as JPEG with decreasing quality,
+    /// and is downscaled by a quarter each time none of them fits.
+    /// Returns `Error::PayloadTooLarge` if even a 1x1 image does not fit,
+    /// and `Error::EncodeFailure` for an animated GIF as [`resize`](Builder::resize) does.
+    pub fn target_payload_size(self, limit: usize) -> Result<Builder<'static>, Error> {
+        let len = self.payload.len();
+        if len <= limit {
+            return Ok(self.into_owned());
+        }
+        let (mut image, _) = self.decode_with_format()?;
+        loop {
+            if let Some(bytes) = encode_within(&image, limit, self.quality)? {
+                return Ok(self.with_image_bytes(bytes));
+            }
+            let (width, height) = (image.width(), image.height());
+            if width <= 1 && height <= 1 {
+                return Err(Error::PayloadTooLarge { len, limit });
+            }
+            image = image.resize_exact(
+                (width * 3 / 4).max(1),
+                (height * 3 / 4).max(1),
+                FilterType::Triangle,
+            );
+        }
+    }
+
+    /// scales the image to fit within `width` x `height` pixels, keeping the aspect ratio
+    ///
+    /// JPEG images are re-encoded as JPEG, and all other images as PNG.
+    /// Returns `Error::EncodeFailure` for an animated GIF, which would lose all but its first frame,
+    /// see [`optimize_gif`](crate::optimize_gif) to downscale it instead.
+    pub fn resize(
+        self,
+        width: u32,
+        height: u32,
+        filter: FilterType,
+    ) -> Result<Builder<'static>, Error> {
+        self.map_image(|image| Ok(image.resize(width, height, filter)))
+    }
+
+    /// crops the image to the `width` x `height` pixels region at (`x`, `y`)
+    ///
+    /// The region is clipped to the image, and is re-encoded as [`resize`](Builder::resize) does.
+    /// Returns `Error::InvalidImageSize` if the clipped region is empty.
+    pub fn crop(self, x: u32, y: u32, width: u32, height: u32) -> Result<Builder<'static>, Error> {
+        self.map_image(|image| {
+            let image = image.crop_imm(x, y, width, height);
+            match (image.width(), image.height()) {
+                (0, _) | (_, 0) => Err(Error::InvalidImageSize {
+                    width: image.width(),
+                    height: image.height(),
+                }),
+                _ => Ok(image),
+            }
+        })
+    }
+
+    /// surrounds the image with a `px` pixels wide border of `color` in RGBA
+    ///
+    /// The image grows by `px` on each side, and is re-encoded as [`resize`](Builder::resize) does.
+    /// Returns `Error::InvalidImageSize` if the grown image would be too large.
+    pub fn with_border(self, px: u32, color: [u8; 4]) -> Result<Builder<'static>, Error> {
+        self.map_image(|image| surround(&image, px, color))
+    }
+
+    /// surrounds the image with `px` pixels of space filled with `color` in RGBA
+    ///
+    /// Transparent parts of the image show `color` too.
+    /// The image grows by `px` on each side, and is re-encoded as [`resize`](Builder::resize) does.
+    /// Returns `Error::InvalidImageSize` if the grown image would be too large.
+    pub fn with_padding(self, px: u32, color: [u8; 4]) -> Result<Builder<'static>, Error> {
+        self.map_image(|image| {
+            let mut canvas = RgbaImage::from_pixel(image.width(), image.height(), Rgba(color));
+            imageops::overlay(&mut canvas, &image, 0, 0);
+            surround(&canvas.into(), px, color)
+        })
+    }
+
+    /// flattens transparency of the image onto `background`
+    ///
+    /// Images without an alpha channel are kept as is.
+    /// Otherwise the image is re-encoded as [`resize`](Builder::resize) does, without an alpha channel.
+    pub fn background(self, background: Background) -> Result<Builder<'static>, Error> {
+        let (image, format) = self.decode_with_format()?;
+        if !image.color().has_alpha() {
+            return Ok(self.into_owned());
+        }
+        let image = flatten(&image.to_rgba8(), background).into();
+        let bytes = encode_as(&image, Some(format), self.quality)?;
+        Ok(self.with_image_bytes(bytes))
+    }
+
+    /// returns a small JPEG preview fitting within `max_px` x `max_px` pixels, keeping the aspect ratio
+    ///
+    /// The builder itself is left untouched and its options are copied to the preview.
+    /// Images that already fit are only re-encoded. Transparency is lost.
+    /// Returns `Error::EncodeFailure` for an animated GIF as [`resize`](Builder::resize) does.
+    pub fn thumbnail(&self, max_px: u32) -> Result<Builder<'static>, Error> {
+        let (mut image, _) = self.decode_with_format()?;
+        if image.width() > max_px || image.height() > max_px {
+            image = image.thumbnail(max_px, max_px);
+        }
+        let bytes = encode_jpeg(&image, self.quality.unwrap_or(THUMBNAIL_QUALITY))?;
+        Ok(self.with_image_bytes(bytes))
+    }
+
+    /// set width and height cells to fit the current terminal and shrink the image to the same size in pixels
+    ///
+    /// `cell` is the size of a cell, as parsed by [`CellSize::parse`] from the reply to [`report_cell_size`](crate::report_cell_size).
+    /// Images already small enough are not re-encoded, and the payload is left unchanged when no terminal is attached.
+    #[cfg(feature = "terminal_size")]
+    pub fn fit_and_downscale(self, cell: CellSize) -> Result<Builder<'static>, Error> {
+        match terminal_size::terminal_size() {
+            Some((terminal_size::Width(w), terminal_size::Height(h))) => {
+                self.fit_and_downscale_to(w, h, cell)
+            }
+            None => Ok(self.into_owned()),
+        }
+    }
+
+    #[cfg(feature = "terminal_size")]
+    fn fit_and_downscale_to(
+        self,
+        columns: u16,
+        rows: u16,
+        cell: CellSize,
+    ) -> Result<Builder<'static>, Error> {
+        let builder = self.fit_to(columns, rows);
+        let px = |cells: u16, points: f64| {
+            (f64::from(cells.max(1)) * points * cell.scale).max(1.0) as u32
+        };
+        let (width, height) = (px(columns, cell.width), px(rows, cell.height));
+        let data = builder.decoded_payload()?;
+        let (w, h) = ImageReader::new(io::Cursor::new(&data))
+            .with_guessed_format()?
+            .into_dimensions()?;
+        drop(data);
+        if w <= width && h <= height {
+            return Ok(builder.into_owned());
+        }
+        builder.resize(width, height, FilterType::Triangle)
+    }
+
+    /// transcodes the image to `format`
+    ///
+    /// The payload is kept as is if it is already in `format`.
+    /// Otherwise the extension of the name, if any, is replaced with `png` or `jpg`.
+    /// Returns an error if the payload cannot be decoded as an image, or is an animated GIF.
+    pub fn convert_to(self, format: Format) -> Result<Builder<'static>, Error> {
+        let data = self.decoded_payload()?;
+        if image::guess_format(&data)? == format.image_format() {
+            drop(data);
+            return Ok(self.into_owned());
+        }
+        let (image, _) = decode_with_format(&data)?;
+        let bytes = encode_as(&image, Some(format.image_format()), self.quality)?;
+        Ok(self.with_image_bytes(bytes))
+    }
+
+    /// rotates and flips the image as the EXIF orientation tag says
+    ///
+    /// The payload is kept as is if the image has no orientation tag or needs no transformation.
+    /// Otherwise it is re-encoded as [`resize`](Builder::resize) does, without the tag.
+    pub fn auto_orient(self) -> Result<Builder<'static>, Error> {
+        match self.oriented_payload()? {
+            Some(bytes) => Ok(self.with_image_bytes(bytes)),
+            None => Ok(self.into_owned()),
+        }
+    }
+
+    pub(crate) fn oriented_payload(&self) -> Result<Option<Vec<u8>>, Error> {
+        let data = self.decoded_payload()?;
+        let reader = ImageReader::new(io::Cursor::new(&data)).with_guessed_format()?;
+        let format = reader.format();
+        let mut decoder = reader.into_decoder()?;
+        let orientation = decoder.orientation()?;
+        if orientation == Orientation::NoTransforms {
+            return Ok(None);
+        }
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        encode_as(&image, format, self.quality).map(Some)
+    }
+
+    /// decodes the image, applies `f` and re-encodes it in the same format where possible
+    pub(crate) fn map_image(
+        self,
+        f: impl FnOnce(DynamicImage) -> Result<DynamicImage, Error>,
+    ) -> Result<Builder<'static>, Error> {
+        let (image, format) = self.decode_with_format()?;
+        let bytes = encode_as(&f(image)?, Some(format), self.quality)?;
+        Ok(self.with_image_bytes(bytes))
+    }
+
+    fn decode_with_format(&self) -> Result<(DynamicImage, ImageFormat), Error> {
+        decode_with_format(&self.decoded_payload()?)
+    }
+
+    pub(crate) fn decode_image(&self) -> Result<DynamicImage, Error> {
+        let data = self.decoded_payload()?;
+        Ok(image::load_from_memory(&data)?)
+    }
+
+    /// replaces the payload, keeping the options
+    ///
+    /// The path is dropped since it no longer matches the payload,
+    /// and the extension of the name is replaced if the format has changed.
+    pub(crate) fn with_image_bytes(&self, bytes: Vec<u8>) -> Builder<'static> {
+        let format = detect_format(&bytes);
+        let mut builder = from_bytes(bytes).with_defaults(self);
+        if format != self.detect_format() {
+            if let (Some(name), Some(ext)) = (&mut builder.name, format.and_then(extension)) {
+                replace_extension(name, ext);
+            }
+        }
+        builder
+    }
+}
+
+/// decodes the image to be re-encoded in its format, rejecting animated GIFs
+fn decode_with_format(data: &[u8]) -> Result<(DynamicImage, ImageFormat), Error> {
+    let format = image::guess_format(data)?;
+    if format == ImageFormat::Gif && crate::gif::is_animated(data) {
+        return Err(Error::EncodeFailure("animated GIF cannot be re-encoded"));
+    }
+    let image = image::load_from_memory_with_format(data, format)?;
+    Ok((image, format))
+}
+
+fn extension(format: FileFormat) -> Option<&'static str> {
+    match format {
+        FileFormat::Png => Some("png"),
+        FileFormat::Jpeg => Some("jpg"),
+        _ => None,
+    }
+}
+
+/// names without an extension are kept as is
+fn replace_extension(name: &mut Vec<u8>, ext: &str) {
+    let start = name.iter().rposition(|b| *b == b'/').map_or(0, |i| i + 1);
+    match name[start..].iter().rposition(|b| *b == b'.') {
+        Some(dot) if dot > 0 => {
+            name.truncate(start + dot + 1);
+            name.extend_from_slice(ext.as_bytes());
+        }
+        _ => {}
+    }
+}
+
+fn flatten(image: &RgbaImage, background: Background) -> RgbImage {
+    let [r, g, b] = match background {
+        Background::Rgb(rgb) => rgb,
+        Background::Auto => {
+            let (sum, weight) = image.pixels().fold((0u64, 0u64), |(sum, weight), p| {
+                let [r, g, b, a] = p.0.map(u64::from);
+                (sum + (r * 299 + g * 587 + b * 114) / 1000 * a, weight + a)
+            });
+            if weight == 0 || sum / weight < 128 {
+                [255; 3]
+            } else {
+                [0; 3]
+            }
+        }
+    };
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [pr, pg, pb, a] = image.get_pixel(x, y).0.map(u32::from);
+        let mix = |c: u32, bg: u8| ((c * a + u32::from(bg) * (255 - a) + 127) / 255) as u8;
+        Rgb([mix(pr, r), mix(pg, g), mix(pb, b)])
+    })
+}
+
+/// returns the image on a canvas filled with `color`, larger by `px` on each side
+///
+/// Returns `Error::InvalidImageSize` if the canvas would exceed `MAX_CANVAS_PIXELS`.
+fn surround(image: &DynamicImage, px: u32, color: [u8; 4]) -> Result<DynamicImage, Error> {
+    let grow = |v: u32| px.checked_mul(2).and_then(|p| v.checked_add(p));
+    let (width, height) = match (grow(image.width()), grow(image.height())) {
+        (Some(w), Some(h)) if u64::from(w) * u64::from(h) <= MAX_CANVAS_PIXELS => (w, h),
+        (w, h) => {
+            return Err(Error::InvalidImageSize {
+                width: w.unwrap_or(u32::MAX),
+                height: h.unwrap_or(u32::MAX),
+            })
+        }
+    };
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba(color));
+    imageops::replace(&mut canvas, &image.to_rgba8(), i64::from(px), i64::from(px));
+    Ok(canvas.into())
+}
+
+/// JPEG is tried from `quality` if given, and then with the lower default qualities
+fn encode_within(
+    image: &DynamicImage,
+    limit: usize,
+    quality: Option<u8>,
+) -> Result<Option<Vec<u8>>, Error> {
+    let png = encode_png(image)?;
+    if png.len() <= limit {
+        return Ok(Some(png));
+    }
+    if image.color().has_alpha() {
+        return Ok(None);
+    }
+    let max = quality.unwrap_or(JPEG_QUALITIES[0]);
+    let qualities = quality
+        .into_iter()
+        .chain(JPEG_QUALITIES.into_iter().filter(|q| *q < max));
+    for quality in qualities {
+        let jpeg = encode_jpeg(image, quality)?;
+        if jpeg.len() <= limit {
+            return Ok(Some(jpeg));
+        }
+    }
+    Ok(None)
+}
+
+fn encode_as(
+    image: &DynamicImage,
+    format: Option<ImageFormat>,
+    quality: Option<u8>,
+) -> Result<Vec<u8>, Error> {
+    match format {
+        Some(ImageFormat::Jpeg) => encode_jpeg(image, quality.unwrap_or(JPEG_QUALITIES[0])),
+        _ => encode_png(image),
+    }
+}
+
+pub(crate) fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    image.write_to(&mut io::Cursor::new(&mut bytes), ImageFormat::Png)?;
+    Ok(bytes)
+}
+
+pub(crate) fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut bytes, quality);
+    image.to_rgb8().write_with_encoder(encoder)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gif(frames: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut data);
+            for _ in 0..frames {
+                let frame = image::Frame::new(DynamicImage::from(noise(4, 4)).into_rgba8());
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        data
+    }
+
+    fn noise(width: u32, height: u32) -> RgbImage {
+        let mut state = 1u32;
+        RgbImage::from_fn(width, height, |_, _| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let [r, g, b, _] = state.to_le_bytes();
+            Rgb([r, g, b])
+        })
+    }
+
+    fn png(image: impl Into<DynamicImage>) -> Vec<u8> {
+        encode_png(&image.into()).unwrap()
+    }
+
+    #[test]
+    fn target_payload_size_keeps_payload_that_fits() {
+        let data = png(noise(4, 4));
+        let builder = from_bytes(data.clone()).name("a.png".to_string());
+        let result = builder.clone().target_payload_size(data.len()).unwrap();
+        assert_eq!(result.build(), builder.build());
+    }
+
+    #[test]
+    fn target_payload_size_opaque() {
+        let data = png(noise(64, 64));
+        let limit = data.len() / 4;
+        let result = from_bytes(data)
+            .width(10)
+            .inline(true)
+            .target_payload_size(limit)
+            .unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert!(bytes.len() <= limit);
+        assert_eq!(
+            image::guess_format(&bytes).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+        assert_eq!(result.get_width(), Some(crate::LengthUnit::Cell(10)));
+        assert_eq!(result.get_inline(), Some(true));
+    }
+
+    #[test]
+    fn target_payload_size_alpha_downscales() {
+        let rgb = noise(32, 32);
+        let rgba = RgbaImage::from_fn(32, 32, |x, y| {
+            let Rgb([r, g, b]) = *rgb.get_pixel(x, y);
+            Rgba([r, g, b, 128])
+        });
+        let data = png(rgba);
+        let limit = data.len() / 2;
+        let result = from_bytes(data).target_payload_size(limit).unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert!(bytes.len() <= limit);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert!(image.width() < 32 && image.height() < 32);
+        assert!(image.color().has_alpha());
+    }
+
+    #[test]
+    fn target_payload_size_animated_gif() {
+        let result = from_bytes(gif(2)).target_payload_size(10);
+        assert!(matches!(result, Err(Error::EncodeFailure(_))));
+    }
+
+    #[test]
+    fn target_payload_size_too_small() {
+        let data = png(noise(8, 8));
+        let len = data.len();
+        let result = from_bytes(data).target_payload_size(10);
+        assert!(matches!(
+            result,
+            Err(Error::PayloadTooLarge { len: l, limit: 10 }) if l == len
+        ));
+    }
+
+    #[test]
+    fn resize() {
+        let result = from_bytes(png(noise(40, 20)))
+            .name("a.png".to_string())
+            .resize(10, 10, FilterType::Nearest)
+            .unwrap();
+        let image = result.decode_image().unwrap();
+        assert_eq!((image.width(), image.height()), (10, 5));
+        assert_eq!(result.get_name(), Some("a.png"));
+    }
+
+    #[test]
+    fn resize_gif() {
+        let result = from_bytes(gif(2)).resize(2, 2, FilterType::Triangle);
+        assert!(matches!(result, Err(Error::EncodeFailure(_))));
+
+        let result = from_bytes(gif(1))
+            .name("a.gif")
+            .resize(2, 2, FilterType::Triangle)
+            .unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+        assert_eq!(result.get_name(), Some("a.png"));
+    }
+
+    #[test]
+    fn resize_keeps_jpeg() {
+        let data = encode_jpeg(&noise(40, 20).into(), 90).unwrap();
+        let result = from_bytes(data)
+            .resize(20, 20, FilterType::Triangle)
+            .unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 10));
+    }
+
+    #[test]
+    fn crop() {
+        let source = noise(40, 20);
+        let result = from_bytes(png(source.clone()))
+            .inline(true)
+            .crop(10, 5, 8, 4)
+            .unwrap();
+        let image = result.decode_image().unwrap().to_rgb8();
+        assert_eq!((image.width(), image.height()), (8, 4));
+        assert_eq!(image.get_pixel(0, 0), source.get_pixel(10, 5));
+        assert_eq!(image.get_pixel(7, 3), source.get_pixel(17, 8));
+        assert_eq!(result.get_inline(), Some(true));
+    }
+
+    #[test]
+    fn crop_clipped() {
+        let result = from_bytes(png(noise(40, 20)))
+            .crop(30, 10, 100, 100)
+            .unwrap();
+        let image = result.decode_image().unwrap();
+        assert_eq!((image.width(), image.height()), (10, 10));
+
+        let result = from_bytes(png(noise(40, 20))).crop(40, 0, 10, 10);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidImageSize {
+                width: 0,
+                height: 10
+            })
+        ));
+    }
+
+    #[test]
+    fn with_border() {
+        let source = RgbaImage::from_fn(4, 2, |x, _| Rgba([0, 0, 0, if x == 0 { 0 } else { 255 }]));
+        let result = from_bytes(png(source.clone()))
+            .inline(true)
+            .with_border(2, [255, 0, 0, 255])
+            .unwrap();
+        let image = result.decode_image().unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (8, 6));
+        assert_eq!(*image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(1, 5), Rgba([255, 0, 0, 255]));
+        assert_eq!(image.get_pixel(2, 2), source.get_pixel(0, 0));
+        assert_eq!(image.get_pixel(5, 3), source.get_pixel(3, 1));
+        assert_eq!(result.get_inline(), Some(true));
+    }
+
+    #[test]
+    fn with_padding() {
+        let source = RgbaImage::from_fn(4, 2, |x, _| Rgba([0, 0, 0, if x == 0 { 0 } else { 255 }]));
+        let result = from_bytes(png(source))
+            .with_padding(1, [255, 255, 255, 255])
+            .unwrap();
+        let image = result.decode_image().unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (6, 4));
+        assert_eq!(*image.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(*image.get_pixel(1, 1), Rgba([255, 255, 255, 255]));
+        assert_eq!(*image.get_pixel(2, 1), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn background() {
+        let source = RgbaImage::from_fn(2, 1, |x, _| Rgba([0, 0, 0, if x == 0 { 0 } else { 128 }]));
+        let result = from_bytes(png(source.clone()))
+            .inline(true)
+            .background(Background::Rgb([255, 0, 0]))
+            .unwrap();
+        let image = result.decode_image().unwrap();
+        assert!(!image.color().has_alpha());
+        let image = image.to_rgb8();
+        assert_eq!(*image.get_pixel(0, 0), Rgb([255, 0, 0]));
+        assert_eq!(*image.get_pixel(1, 0), Rgb([127, 0, 0]));
+        assert_eq!(result.get_inline(), Some(true));
+
+        let result = from_bytes(png(source))
+            .background(Background::Auto)
+            .unwrap();
+        let image = result.decode_image().unwrap().to_rgb8();
+        assert_eq!(*image.get_pixel(0, 0), Rgb([255, 255, 255]));
+
+        let light = RgbaImage::from_pixel(1, 1, Rgba([240, 240, 240, 128]));
+        let result = from_bytes(png(light)).background(Background::Auto).unwrap();
+        let image = result.decode_image().unwrap().to_rgb8();
+        assert_eq!(*image.get_pixel(0, 0), Rgb([120, 120, 120]));
+    }
+
+    #[test]
+    fn background_opaque() {
+        let builder = from_bytes(png(noise(4, 4)));
+        let result = builder.clone().background(Background::Auto).unwrap();
+        assert_eq!(result.build(), builder.build());
+    }
+
+    #[test]
+    fn quality() {
+        let data = png(noise(32, 32));
+        let sizes = [90, 50, 10].map(|quality| {
+            let result = from_bytes(data.clone())
+                .quality(quality)
+                .convert_to(Format::Jpeg)
+                .unwrap();
+            assert_eq!(result.get_quality(), Some(quality));
+            result.decoded_payload().unwrap().len()
+        });
+        assert!(sizes[0] > sizes[1] && sizes[1] > sizes[2]);
+
+        let result = from_bytes(data.clone()).quality(10).thumbnail(32).unwrap();
+        assert_eq!(result.decoded_payload().unwrap().len(), sizes[2]);
+
+        let limit = sizes[2];
+        let result = from_bytes(data)
+            .quality(10)
+            .target_payload_size(limit)
+            .unwrap();
+        let image = result.decode_image().unwrap();
+        assert_eq!((image.width(), image.height()), (32, 32));
+    }
+
+    #[test]
+    fn with_border_too_large() {
+        let result = from_bytes(png(noise(4, 2))).with_border(u32::MAX / 2, [0; 4]);
+        assert!(matches!(result, Err(Error::InvalidImageSize { .. })));
+
+        let result = from_bytes(png(noise(4, 2))).with_padding(u32::MAX / 2, [0; 4]);
+        assert!(matches!(result, Err(Error::InvalidImageSize { .. })));
+
+        let result = from_bytes(png(noise(4, 2))).with_border(5000, [0; 4]);
+        assert!(matches!(
+            result,
+            Err(Error::InvalidImageSize {
+                width: 10004,
+                height: 10002
+            })
+        ));
+    }
+
+    #[test]
+    fn thumbnail() {
+        let builder = from_bytes(png(noise(64, 32))).width(5);
+        let result = builder.thumbnail(16).unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+        assert_eq!(result.get_width(), Some(crate::LengthUnit::Cell(5)));
+
+        let original = builder.decode_image().unwrap();
+        assert_eq!((original.width(), original.height()), (64, 32));
+    }
+
+    #[test]
+    fn thumbnail_animated_gif() {
+        let result = from_bytes(gif(2)).thumbnail(2);
+        assert!(matches!(result, Err(Error::EncodeFailure(_))));
+    }
+
+    #[test]
+    fn thumbnail_does_not_upscale() {
+        let result = from_bytes(png(noise(8, 4))).thumbnail(16).unwrap();
+        let image = result.decode_image().unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+    }
+
+    /// JPEG with an APP1 segment holding only the orientation tag
+    fn jpeg_with_orientation(image: &DynamicImage, orientation: u16) -> Vec<u8> {
+        let jpeg = encode_jpeg(image, 90).unwrap();
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut data = jpeg[..2].to_vec();
+        data.extend_from_slice(&[0xff, 0xe1]);
+        data.extend_from_slice(&(exif.len() as u16 + 2).to_be_bytes());
+        data.extend_from_slice(&exif);
+        data.extend_from_slice(&jpeg[2..]);
+        data
+    }
+
+    #[test]
+    fn auto_orient() {
+        let data = jpeg_with_orientation(&noise(40, 20).into(), 6);
+        let result = from_bytes(data)
+            .name("a.jpg".to_string())
+            .auto_orient()
+            .unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 40));
+        assert_eq!(result.get_name(), Some("a.jpg"));
+    }
+
+    #[test]
+    fn auto_orient_keeps_payload_without_tag() {
+        let data = jpeg_with_orientation(&noise(40, 20).into(), 1);
+        let builder = from_bytes(data);
+        let result = builder.clone().auto_orient().unwrap();
+        assert_eq!(result.build(), builder.build());
+
+        let builder = from_bytes(png(noise(4, 4)));
+        let result = builder.clone().auto_orient().unwrap();
+        assert_eq!(result.build(), builder.build());
+    }
+
+    #[cfg(feature = "terminal_size")]
+    #[test]
+    fn fit_and_downscale_to() {
+        let cell = CellSize {
+            width: 4.0,
+            height: 8.0,
+            scale: 2.0,
+        };
+        let result = from_bytes(png(noise(160, 160)))
+            .fit_and_downscale_to(10, 5, cell)
+            .unwrap();
+        assert_eq!(result.get_width(), Some(crate::LengthUnit::Cell(10)));
+        assert_eq!(result.get_height(), Some(crate::LengthUnit::Cell(5)));
+        let image = result.decode_image().unwrap();
+        assert_eq!((image.width(), image.height()), (80, 80));
+
+        let data = png(noise(40, 40));
+        let result = from_bytes(data.clone())
+            .fit_and_downscale_to(10, 5, cell)
+            .unwrap();
+        assert_eq!(result.decoded_payload().unwrap(), data);
+    }
+
+    #[test]
+    fn convert_to() {
+        let result = from_bytes(png(noise(8, 4)))
+            .inline(true)
+            .convert_to(Format::Jpeg)
+            .unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(result.get_inline(), Some(true));
+
+        let result = result.convert_to(Format::Png).unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+    }
+
+    #[test]
+    fn convert_to_replaces_extension() {
+        let result = from_bytes(png(noise(4, 4)))
+            .name("dir.d/a.b.png")
+            .convert_to(Format::Jpeg)
+            .unwrap();
+        assert_eq!(result.get_name(), Some("dir.d/a.b.jpg"));
+
+        let result = result.convert_to(Format::Png).unwrap();
+        assert_eq!(result.get_name(), Some("dir.d/a.b.png"));
+
+        for name in ["a", ".png", "a.d/b"] {
+            let result = from_bytes(png(noise(4, 4)))
+                .name(name)
+                .convert_to(Format::Jpeg)
+                .unwrap();
+            assert_eq!(result.get_name(), Some(name));
+        }
+    }
+
+    #[test]
+    fn convert_to_same_format() {
+        let builder = from_bytes(png(noise(4, 4)));
+        let result = builder.clone().convert_to(Format::Png).unwrap();
+        assert_eq!(result.build(), builder.build());
+    }
+
+    #[test]
+    fn convert_to_animated_gif() {
+        let result = from_bytes(gif(2)).convert_to(Format::Png);
+        assert!(matches!(result, Err(Error::EncodeFailure(_))));
+
+        let result = from_bytes(gif(1)).convert_to(Format::Png).unwrap();
+        let bytes = result.decoded_payload().unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+    }
+
+    #[test]
+    fn convert_to_invalid_image() {
+        let result = from_bytes(b"not an image".to_vec()).convert_to(Format::Png);
+        assert!(matches!(result, Err(Error::Image(_))));
+    }
+
+    #[test]
+    fn target_payload_size_invalid_image() {
+        let result = from_bytes(b"not an image".to_vec()).target_payload_size(4);
+        assert!(matches!(result, Err(Error::Image(_))));
+    }
+}
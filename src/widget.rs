@@ -0,0 +1,106 @@
+use std::num::NonZeroU16;
+
+use ratatui_core::{
+    buffer::{Buffer, CellDiffOption},
+    layout::Rect,
+    widgets::Widget,
+};
+
+use crate::{Builder, LengthUnit};
+
+/// ratatui widget that displays an inline image
+///
+/// The sequence is written into the top-left cell of the area and the remaining cells are skipped
+/// so that ratatui does not overwrite the image.
+/// Width and height are set to the size of the area in cells unless the builder sets them.
+///
+/// # Examples
+///
+/// ```
+/// use ratatui_core::{buffer::Buffer, layout::Rect, widgets::Widget};
+///
+/// let area = Rect::new(0, 0, 10, 5);
+/// let mut buf = Buffer::empty(area);
+/// let image = iterm2img::from_bytes("abc".as_bytes());
+/// iterm2img::InlineImage::new(image).render(area, &mut buf);
+///
+/// assert_eq!(
+///     buf[(0, 0)].symbol(),
+///     "\x1b]1337;File=size=3;width=10;height=5:YWJj\u{0007}"
+/// );
+/// ```
+pub struct InlineImage<'a> {
+    builder: Builder<'a>,
+}
+
+impl<'a> InlineImage<'a> {
+    /// returns widget from builder
+    pub fn new(builder: Builder<'a>) -> InlineImage<'a> {
+        InlineImage { builder }
+    }
+}
+
+impl Widget for InlineImage<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = area.intersection(buf.area);
+        if area.is_empty() {
+            return;
+        }
+
+        let mut builder = self.builder;
+        builder.width = builder
+            .width
+            .or(Some(LengthUnit::Cell(u64::from(area.width))));
+        builder.height = builder
+            .height
+            .or(Some(LengthUnit::Cell(u64::from(area.height))));
+
+        for position in area.positions() {
+            if let Some(cell) = buf.cell_mut(position) {
+                cell.reset();
+                cell.set_diff_option(CellDiffOption::Skip);
+            }
+        }
+        if let Some(cell) = buf.cell_mut(area.as_position()) {
+            // the computed width of an escape sequence is meaningless
+            let width = CellDiffOption::ForcedWidth(NonZeroU16::MIN);
+            cell.set_symbol(&builder.build()).set_diff_option(width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_bytes;
+
+    #[test]
+    fn render() {
+        let area = Rect::new(0, 0, 10, 5);
+        let mut buf = Buffer::empty(area);
+        let image = from_bytes("abc".as_bytes()).width(3).height_px(100);
+        InlineImage::new(image).render(Rect::new(2, 1, 4, 3), &mut buf);
+
+        assert_eq!(
+            buf[(2, 1)].symbol(),
+            "\x1b]1337;File=size=3;width=3;height=100px:YWJj\u{0007}"
+        );
+        assert_eq!(
+            buf[(2, 1)].diff_option,
+            CellDiffOption::ForcedWidth(NonZeroU16::MIN)
+        );
+        for position in Rect::new(2, 1, 4, 3).positions().skip(1) {
+            assert_eq!(buf[position].diff_option, CellDiffOption::Skip);
+        }
+        assert_eq!(buf[(0, 0)].diff_option, CellDiffOption::None);
+        assert_eq!(buf[(6, 1)].diff_option, CellDiffOption::None);
+    }
+
+    #[test]
+    fn render_outside() {
+        let area = Rect::new(0, 0, 10, 5);
+        let mut buf = Buffer::empty(area);
+        InlineImage::new(from_bytes("abc".as_bytes())).render(Rect::new(20, 20, 4, 3), &mut buf);
+        assert_eq!(buf, Buffer::empty(area));
+    }
+}